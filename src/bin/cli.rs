@@ -1,11 +1,31 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use clap::{Args, Parser, Subcommand};
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use futures_util::future::join_all;
 use thiserror::Error;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::time;
+use tokio_util::sync::CancellationToken;
 use tracing_subscriber::filter::LevelFilter;
 
+use netcheck::config::{Config, RunnerConfig, Watcher};
+use netcheck::notifier::{Channel, Notifier, Webhook};
 use netcheck::runner;
-use netcheck::{log, metric};
+use netcheck::supervisor::Supervisor;
+use netcheck::{config, log, metric};
+
+const DEFAULT_TARGET: &str = "external=https://one.one.one.one,https://dns.google";
+const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 500;
+const DEFAULT_TIMEOUT_MS: u64 = 500;
+const DEFAULT_WAIT_TIME_SECONDS: u64 = 2;
+const DEFAULT_FAILURE_THRESHOLD: u8 = 5;
+const DEFAULT_RECOVERY_THRESHOLD: u8 = 5;
+const DEFAULT_CONCURRENCY: usize = 10;
 
 #[derive(Parser, Debug)]
 #[command(name = "netcheck")]
@@ -38,48 +58,293 @@ struct Cli {
     command: Commands,
 }
 
+impl Cli {
+    /// Validate the arguments which depend on each other and clap can not check on its own.
+    fn validate(&self) -> Result<(), runner::TargetParseError> {
+        match &self.command {
+            Commands::Run(args) => runner::validate_names(&args.shared.target),
+            Commands::Check(args) => runner::validate_names(&args.shared.target),
+        }
+    }
+}
+
 #[derive(Subcommand, PartialEq, Debug)]
 enum Commands {
     Run(Run),
+    Check(Check),
 }
 
+/// SharedArgs are the flags of both the run and check commands, selecting the targets and how
+/// they are checked.
 #[derive(Args, PartialEq, Debug)]
-#[command(about = "Runs the netcheck service")]
-#[command(long_about = "Runs the netcheck service and checks the network using the passed targets")]
-struct Run {
+struct SharedArgs {
     #[arg(short)]
     #[arg(long)]
     #[arg(help = "List of targets to check if a network connection is attainable")]
-    #[arg(default_value = "external=https://one.one.one.one,https://dns.google")]
+    #[arg(long_help = format!("List of targets to check if a network connection is attainable, \
+        added to the targets of the config file\n\n[default: {}]", DEFAULT_TARGET))]
     target: Vec<runner::Target>,
 
+    #[arg(short)]
+    #[arg(long)]
+    #[arg(help = "Path to a YAML or TOML file defining the targets and runner settings")]
+    config: Option<PathBuf>,
+
     #[arg(long = "connect")]
     #[arg(help = "Connect timeout milliseconds to be considered a failure")]
-    #[arg(default_value = "500")]
-    connect_timeout_ms: u64,
+    #[arg(long_help = format!("Connect timeout milliseconds to be considered a failure, \
+        overrides the config file\n\n[default: {}]", DEFAULT_CONNECT_TIMEOUT_MS))]
+    connect_timeout_ms: Option<u64>,
 
     #[arg(long = "timeout")]
     #[arg(help = "Timeout milliseconds to be considered a failure")]
-    #[arg(default_value = "500")]
-    timeout_ms: u64,
+    #[arg(long_help = format!("Timeout milliseconds to be considered a failure, \
+        overrides the config file\n\n[default: {}]", DEFAULT_TIMEOUT_MS))]
+    timeout_ms: Option<u64>,
+
+    #[arg(long = "wait-ms")]
+    #[arg(help = "Time to wait between checks in milliseconds, for intervals below a second")]
+    #[arg(
+        long_help = "Time to wait between checks in milliseconds, for intervals below a second, \
+        overrides the config file"
+    )]
+    wait_time_ms: Option<u64>,
+
+    #[arg(long)]
+    #[arg(help = "Failures in a row to determine if target is failing")]
+    #[arg(long_help = format!("Failures in a row to determine if target is failing, \
+        overrides the config file\n\n[default: {} when running, the number of iterations when \
+        checking]", DEFAULT_FAILURE_THRESHOLD))]
+    failure_threshold: Option<u8>,
+
+    #[arg(long)]
+    #[arg(help = "Urls of a target which need to be available for it to be available")]
+    #[arg(
+        long_help = "Urls of a target which need to be available for it to be available, \
+        any, all or a number, overrides the config file\n\n[default: any]"
+    )]
+    quorum: Option<runner::Quorum>,
+
+    #[arg(long)]
+    #[arg(help = "Maximum urls of a target checked at once")]
+    #[arg(long_help = format!("Maximum urls of a target checked at once when fanning out, \
+        overrides the config file\n\n[default: {}]", DEFAULT_CONCURRENCY))]
+    concurrency: Option<usize>,
+}
+
+#[derive(Args, PartialEq, Debug)]
+#[command(about = "Runs the netcheck service")]
+#[command(long_about = "Runs the netcheck service and checks the network using the passed targets")]
+struct Run {
+    #[command(flatten)]
+    shared: SharedArgs,
 
     #[arg(short = 'w')]
     #[arg(long = "wait")]
+    #[arg(conflicts_with = "wait_time_ms")]
     #[arg(help = "Time to wait between requests in seconds")]
-    #[arg(default_value = "2")]
-    wait_time_seconds: u64,
+    #[arg(long_help = format!("Time to wait between requests in seconds, \
+        overrides the config file\n\n[default: {}]", DEFAULT_WAIT_TIME_SECONDS))]
+    wait_time_seconds: Option<u64>,
 
     #[arg(long)]
-    #[arg(help = "Failures in a row to determine if target is failing")]
-    #[arg(default_value = "5")]
-    failure_threshold: u8,
+    #[arg(help = "Maximum random milliseconds added to each wait, to spread out requests")]
+    #[arg(
+        long_help = "Maximum random milliseconds added to each wait, to spread out requests, \
+        overrides the config file\n\n[default: 0]"
+    )]
+    jitter_ms: Option<u64>,
+
+    #[arg(long)]
+    #[arg(help = "Maximum random milliseconds to wait before the first request of each target")]
+    #[arg(
+        long_help = "Maximum random milliseconds to wait before the first request of each \
+        target, so targets do not start in lockstep, overrides the config file\n\n[default: 0]"
+    )]
+    start_offset_ms: Option<u64>,
+
+    #[arg(long)]
+    #[arg(help = "Successes in a row to determine if a failing target has recovered")]
+    #[arg(long_help = format!("Successes in a row to determine if a failing target has recovered, \
+        overrides the config file\n\n[default: {}]", DEFAULT_RECOVERY_THRESHOLD))]
+    recovery_threshold: Option<u8>,
+
+    #[arg(long)]
+    #[arg(help = "Check every url of a target each interval instead of one url per interval")]
+    #[arg(
+        long_help = "Check every url of a target concurrently each interval instead of one url \
+        per interval, overrides the config file\n\n[default: false]"
+    )]
+    fan_out: Option<bool>,
+
+    #[arg(long)]
+    #[arg(help = "Url to POST a JSON notification to when a target changes status")]
+    #[arg(
+        long_help = "Url to POST a JSON notification to when a target changes status, added to \
+        the notifiers of the config file"
+    )]
+    webhook: Vec<String>,
+
+    #[arg(long = "reload-interval")]
+    #[arg(
+        help = "Seconds between checking the config file for changes, SIGHUP reloads immediately"
+    )]
+    #[arg(default_value = "10")]
+    reload_interval_seconds: u64,
+
+    #[arg(long = "shutdown-timeout")]
+    #[arg(help = "Seconds to wait for in-flight checks and notifications when shutting down")]
+    #[arg(
+        long_help = "Seconds to wait for in-flight checks and notifications on SIGTERM or \
+        Ctrl-C, runners still checking afterwards are aborted"
+    )]
+    #[arg(default_value = "10")]
+    shutdown_timeout_seconds: u64,
+}
+
+#[derive(Args, PartialEq, Debug)]
+#[command(about = "Checks every target once and exits")]
+#[command(
+    long_about = "Checks every url of every target for a number of rounds, prints a summary and \
+    exits with status 1 if any target is unavailable, for use in deploy pipelines and init \
+    containers"
+)]
+struct Check {
+    #[command(flatten)]
+    shared: SharedArgs,
+
+    #[arg(short = 'n')]
+    #[arg(long)]
+    #[arg(help = "Rounds of checks of every url")]
+    #[arg(default_value = "1")]
+    #[arg(value_parser = clap::value_parser!(u64).range(1..))]
+    iterations: u64,
+
+    #[arg(short)]
+    #[arg(long)]
+    #[arg(help = "Format of the summary")]
+    #[arg(value_enum)]
+    #[arg(default_value_t = Output::Table)]
+    output: Output,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Debug)]
+enum Output {
+    Table,
+    Json,
+}
+
+/// Load the config file and add the targets passed on the command line, falling back to the
+/// default target when neither declares any.
+fn load_config(
+    path: Option<&PathBuf>,
+    targets: &[runner::Target],
+) -> Result<Config, Error> {
+    let config = match path {
+        Some(path) => Config::from_file(path)?,
+        None => Config::default(),
+    }
+    .with_targets(targets.to_vec());
+
+    if config.targets.is_empty() {
+        let target = runner::Target::from_str(DEFAULT_TARGET).expect("default target not valid");
+        return Ok(config.with_targets(vec![target]));
+    }
+
+    Ok(config)
+}
+
+/// The settings used for anything not set by the config file or command line.
+fn defaults() -> RunnerConfig {
+    RunnerConfig {
+        connect_timeout_ms: Some(DEFAULT_CONNECT_TIMEOUT_MS),
+        timeout_ms: Some(DEFAULT_TIMEOUT_MS),
+        wait_time_seconds: Some(DEFAULT_WAIT_TIME_SECONDS),
+        failure_threshold: Some(DEFAULT_FAILURE_THRESHOLD),
+        recovery_threshold: Some(DEFAULT_RECOVERY_THRESHOLD),
+        concurrency: Some(DEFAULT_CONCURRENCY),
+        ..Default::default()
+    }
+}
+
+impl SharedArgs {
+    /// The settings passed on the command line to either command.
+    fn overrides(&self) -> RunnerConfig {
+        RunnerConfig {
+            connect_timeout_ms: self.connect_timeout_ms,
+            timeout_ms: self.timeout_ms,
+            wait_time_ms: self.wait_time_ms,
+            failure_threshold: self.failure_threshold,
+            quorum: self.quorum,
+            concurrency: self.concurrency,
+            ..Default::default()
+        }
+    }
+}
+
+impl Run {
+    /// Load the config file and add the targets passed on the command line.
+    fn config(&self) -> Result<Config, Error> {
+        load_config(self.shared.config.as_ref(), &self.shared.target)
+    }
+
+    /// Load the targets to run with every setting resolved, and the channels to notify.
+    fn load(&self) -> Result<(Vec<config::TargetConfig>, Vec<Channel>), Error> {
+        let config = self.config()?;
+        let mut channels = config.notifiers.clone();
+        channels.extend(
+            self.webhook
+                .iter()
+                .map(|url| Channel::Webhook(Webhook::new(url.clone()))),
+        );
+
+        Ok((config.resolve(&defaults(), &self.overrides()), channels))
+    }
+
+    /// The settings passed on the command line, which take precedence over the config file.
+    fn overrides(&self) -> RunnerConfig {
+        RunnerConfig {
+            wait_time_seconds: self.wait_time_seconds,
+            jitter_ms: self.jitter_ms,
+            start_offset_ms: self.start_offset_ms,
+            recovery_threshold: self.recovery_threshold,
+            fan_out: self.fan_out,
+            ..self.shared.overrides()
+        }
+    }
+}
+
+impl Check {
+    /// The settings passed on the command line, which take precedence over the config file.
+    ///
+    /// Every url is checked each round, and unless set otherwise a target is unavailable once it
+    /// fails every round.
+    fn overrides(&self) -> RunnerConfig {
+        RunnerConfig {
+            jitter_ms: Some(0),
+            start_offset_ms: Some(0),
+            failure_threshold: self
+                .shared
+                .failure_threshold
+                .or(Some(self.iterations.min(u8::MAX as u64) as u8)),
+            fan_out: Some(true),
+            ..self.shared.overrides()
+        }
+    }
 }
 
 #[tokio::main]
 #[tracing::instrument(level = "info")]
-async fn main() -> Result<(), Error> {
+async fn main() -> Result<ExitCode, Error> {
     let cli = Cli::parse();
+    if let Err(e) = cli.validate() {
+        Cli::command().error(ErrorKind::ValueValidation, e).exit();
+    }
     let mut log_builder = log::Builder::new();
+    if let Commands::Check(_) = cli.command {
+        // Keep stdout for the summary, only logging what went wrong unless asked otherwise.
+        log_builder.with_level(LevelFilter::WARN).with_stderr(true);
+    }
     if let Some(log_level) = cli.log_level {
         log_builder.with_level(log_level);
     }
@@ -90,9 +355,107 @@ async fn main() -> Result<(), Error> {
         Commands::Run(args) => {
             run(args, cli.metrics_port).await?;
         }
+        Commands::Check(args) => {
+            return check(args).await;
+        }
     }
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Check every target for the requested rounds, print the summary and fail if any target is
+/// unavailable.
+#[tracing::instrument(level = "info")]
+async fn check(args: Check) -> Result<ExitCode, Error> {
+    let (reports, summary) = check_targets(&args).await?;
+    println!("{}", summary);
+
+    if reports.iter().any(|report| report.is_unavailable) {
+        return Ok(ExitCode::FAILURE);
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Run every target for the requested rounds at once, returning their reports and the summary
+/// in the requested format.
+async fn check_targets(args: &Check) -> Result<(Vec<runner::TargetReport>, String), Error> {
+    let registry = runner::Registry::new();
+    let runners = load_config(args.shared.config.as_ref(), &args.shared.target)?
+        .runner_builders(&defaults(), &args.overrides())?
+        .into_iter()
+        .map(|builder| {
+            builder
+                .registry(registry.clone())
+                .run_for_iterations(args.iterations)
+                .build()
+        })
+        .collect::<Vec<_>>();
+
+    for result in join_all(runners.iter().map(|runner| runner.run())).await {
+        result?;
+    }
+
+    let reports = registry.targets();
+    let summary = match args.output {
+        Output::Table => table(&reports),
+        Output::Json => serde_json::to_string_pretty(&reports)?,
+    };
+
+    Ok((reports, summary))
+}
+
+/// Render the reports as a table with a row for each url.
+fn table(reports: &[runner::TargetReport]) -> String {
+    let status = |is_unavailable: bool, is_degraded: bool| match (is_unavailable, is_degraded) {
+        (true, _) => "unavailable",
+        (false, true) => "degraded",
+        (false, false) => "available",
+    };
+
+    let mut rows = vec![[
+        "TARGET".to_string(),
+        "STATUS".to_string(),
+        "URL".to_string(),
+        "URL STATUS".to_string(),
+        "LATENCY".to_string(),
+        "ERROR".to_string(),
+    ]];
+    for report in reports {
+        for url in &report.urls {
+            rows.push([
+                report.name.clone(),
+                format!(
+                    "{} ({}/{})",
+                    status(report.is_unavailable, report.is_degraded),
+                    report.available_urls,
+                    report.urls.len()
+                ),
+                url.url.clone(),
+                status(url.is_unavailable, url.is_degraded).to_string(),
+                url.last_latency_ms
+                    .map(|latency| format!("{:.1}ms", latency))
+                    .unwrap_or_else(|| "-".to_string()),
+                url.last_error.clone().unwrap_or_else(|| "-".to_string()),
+            ]);
+        }
+    }
+
+    let widths = (0..6)
+        .map(|column| rows.iter().map(|row| row[column].len()).max().unwrap_or(0))
+        .collect::<Vec<_>>();
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 #[tracing::instrument(level = "info")]
@@ -101,37 +464,101 @@ async fn run(
     metrics_port: Option<u16>,
 ) -> Result<(), Error> {
     let metrics = metric::MetricProvider::new();
-    let background_threads: Arc<Mutex<Vec<tokio::task::JoinHandle<()>>>> =
-        Arc::new(Mutex::new(Vec::new()));
+    let notifier = Notifier::new();
+    let supervisor = Arc::new(Mutex::new(
+        Supervisor::with_registry(metrics.registry.clone()).with_notifier(notifier.clone()),
+    ));
 
-    let mut background_threads_locked = background_threads
+    let (targets, channels) = args.load()?;
+    notifier.set_channels(channels);
+    supervisor
         .lock()
-        .expect("Failed to remember our background threads");
-
-    let targets = args.target;
-
-    for target in targets {
-        background_threads_locked.push(tokio::spawn(async move {
-            let runner = runner::RunnerBuilder::new()
-                .target(target)
-                .connect_timeout_ms(args.connect_timeout_ms)
-                .timeout_ms(args.timeout_ms)
-                .wait_time_seconds(args.wait_time_seconds)
-                .build();
-            match runner.run().await {
-                Err(e) => {
-                    tracing::error!("handler error: {}", e);
+        .expect("Failed to lock the supervisor")
+        .reconcile(targets)?;
+
+    let shutdown = CancellationToken::new();
+    tokio::spawn(wait_for_signal(shutdown.clone()));
+
+    let shutdown_timeout = Duration::from_secs(args.shutdown_timeout_seconds);
+    if let Some(path) = args.shared.config.clone() {
+        let mut watcher =
+            Watcher::new(&path, Duration::from_secs(args.reload_interval_seconds)).await?;
+        let supervisor = supervisor.clone();
+        let notifier = notifier.clone();
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = watcher.changed() => {}
+                    _ = shutdown.cancelled() => return,
+                }
+                let reconciled = args.load().and_then(|(targets, channels)| {
+                    supervisor
+                        .lock()
+                        .expect("Failed to lock the supervisor")
+                        .reconcile(targets)?;
+                    notifier.set_channels(channels);
+                    Ok(())
+                });
+                if let Err(e) = reconciled {
+                    tracing::error!("failed to reload config, keeping current targets: {}", e);
                 }
-                _ => {}
             }
-        }));
+        });
     }
 
-    metrics.listen(metrics_port).await?;
+    metrics
+        .listen(metrics_port, supervisor.clone(), shutdown.clone())
+        .await?;
+
+    // The server also stops if it fails, so make sure the runners are told to stop.
+    shutdown.cancel();
+    // The runners and the notifications share a single deadline so the whole shutdown stays
+    // within the timeout.
+    let deadline = time::Instant::now() + shutdown_timeout;
+    let stopping = supervisor
+        .lock()
+        .expect("Failed to lock the supervisor")
+        .shutdown();
+    let aborted = stopping
+        .wait(deadline.saturating_duration_since(time::Instant::now()))
+        .await;
+    if !aborted.is_empty() {
+        tracing::warn!(
+            "aborted runners still checking after {:?}: {}",
+            shutdown_timeout,
+            aborted.join(", ")
+        );
+    }
+    if time::timeout_at(deadline, notifier.flush()).await.is_err() {
+        tracing::warn!(
+            "dropped notifications still sending after {:?}",
+            shutdown_timeout
+        );
+    }
+    metrics.shutdown()?;
+    tracing::info!("shutdown complete");
 
     Ok(())
 }
 
+/// Cancel the token on SIGTERM or Ctrl-C, starting a graceful shutdown.
+async fn wait_for_signal(shutdown: CancellationToken) {
+    let mut terminate = match signal(SignalKind::terminate()) {
+        Ok(terminate) => terminate,
+        Err(e) => {
+            tracing::error!("failed to listen for SIGTERM: {}", e);
+            return;
+        }
+    };
+
+    tokio::select! {
+        _ = terminate.recv() => tracing::info!("received SIGTERM, shutting down"),
+        _ = tokio::signal::ctrl_c() => tracing::info!("received Ctrl-C, shutting down"),
+    }
+    shutdown.cancel();
+}
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("{source}")]
@@ -151,6 +578,18 @@ pub enum Error {
         #[from]
         source: metric::Error,
     },
+
+    #[error("{source}")]
+    ConfigError {
+        #[from]
+        source: config::Error,
+    },
+
+    #[error("{source}")]
+    JsonError {
+        #[from]
+        source: serde_json::Error,
+    },
 }
 
 #[cfg(test)]
@@ -161,28 +600,35 @@ mod tests {
 
     #[test]
     fn test_cli() {
-        let cli = Cli::parse_from(&["netcheck", "run"]);
+        let cli = Cli::parse_from(["netcheck", "run"]);
         assert_eq!(
             cli.command,
             Commands::Run(Run {
-                target: vec![runner::Target::new(
-                    "external".to_string(),
-                    vec![
-                        "https://one.one.one.one".parse().unwrap(),
-                        "https://dns.google".parse().unwrap(),
-                    ],
-                ),],
-                connect_timeout_ms: 500,
-                timeout_ms: 500,
-                wait_time_seconds: 2,
-                failure_threshold: 5,
+                shared: SharedArgs {
+                    target: vec![],
+                    config: None,
+                    connect_timeout_ms: None,
+                    timeout_ms: None,
+                    wait_time_ms: None,
+                    failure_threshold: None,
+                    quorum: None,
+                    concurrency: None,
+                },
+                wait_time_seconds: None,
+                jitter_ms: None,
+                start_offset_ms: None,
+                recovery_threshold: None,
+                fan_out: None,
+                webhook: vec![],
+                reload_interval_seconds: 10,
+                shutdown_timeout_seconds: 10,
             })
         );
     }
 
     #[test]
     fn test_cli_with_args() {
-        let cli = Cli::parse_from(&[
+        let cli = Cli::parse_from([
             "netcheck",
             "run",
             "--target",
@@ -195,29 +641,249 @@ mod tests {
             "1",
             "--wait",
             "1",
+            "--jitter-ms",
+            "100",
+            "--start-offset-ms",
+            "500",
             "--failure-threshold",
             "1",
+            "--recovery-threshold",
+            "10",
+            "--quorum",
+            "all",
+            "--fan-out",
+            "true",
+            "--concurrency",
+            "2",
+            "--webhook",
+            "https://hooks.example.com",
         ]);
         assert_eq!(
             cli.command,
             Commands::Run(Run {
-                target: vec![
-                    runner::Target::new(
-                        "internal".to_string(),
-                        vec![
-                            "https://google.com".parse().unwrap(),
-                            "https://example.com".parse().unwrap(),
-                        ],
-                    ),
-                    runner::Target::new(
-                        "external".to_string(),
-                        vec!["https://example.com".parse().unwrap()],
-                    ),
+                shared: SharedArgs {
+                    target: vec![
+                        runner::Target::new(
+                            "internal".to_string(),
+                            vec![
+                                "https://google.com".parse().unwrap(),
+                                "https://example.com".parse().unwrap(),
+                            ],
+                        ),
+                        runner::Target::new(
+                            "external".to_string(),
+                            vec!["https://example.com".parse().unwrap()],
+                        ),
+                    ],
+                    config: None,
+                    connect_timeout_ms: Some(1),
+                    timeout_ms: Some(1),
+                    wait_time_ms: None,
+                    failure_threshold: Some(1),
+                    quorum: Some(runner::Quorum::All),
+                    concurrency: Some(2),
+                },
+                wait_time_seconds: Some(1),
+                jitter_ms: Some(100),
+                start_offset_ms: Some(500),
+                recovery_threshold: Some(10),
+                fan_out: Some(true),
+                webhook: vec!["https://hooks.example.com".to_string()],
+                reload_interval_seconds: 10,
+                shutdown_timeout_seconds: 10,
+            })
+        );
+    }
+
+    #[test]
+    fn test_cli_run_config_default_target() {
+        let cli = Cli::parse_from(["netcheck", "run"]);
+        let Commands::Run(args) = cli.command else {
+            panic!("expected the run command")
+        };
+
+        assert_eq!(
+            args.config().unwrap().targets,
+            vec![config::TargetConfig::from(runner::Target::new(
+                "external".to_string(),
+                vec![
+                    "https://one.one.one.one".parse().unwrap(),
+                    "https://dns.google".parse().unwrap(),
                 ],
-                connect_timeout_ms: 1,
-                timeout_ms: 1,
-                wait_time_seconds: 1,
-                failure_threshold: 1,
+            ))]
+        );
+    }
+
+    #[test]
+    fn test_cli_run_config_file() {
+        let path = std::env::temp_dir().join(format!("netcheck-cli-{}.yaml", std::process::id()));
+        std::fs::write(
+            &path,
+            "defaults:\n  timeout_ms: 100\n  wait_time_seconds: 7\ntargets:\n  \
+             - name: internal\n    urls: [\"http://hello:9111\"]\n    failure_threshold: 3\n\
+             notifiers:\n  - type: webhook\n    url: http://hooks:8080/file\n",
+        )
+        .unwrap();
+
+        let cli = Cli::parse_from([
+            "netcheck",
+            "run",
+            "--config",
+            path.to_str().unwrap(),
+            "--target",
+            "external=https://example.com",
+            "--wait",
+            "1",
+            "--webhook",
+            "http://hooks:8080/cli",
+        ]);
+        let Commands::Run(args) = cli.command else {
+            panic!("expected the run command")
+        };
+        let config = args.config().unwrap();
+        let (targets, channels) = args.load().unwrap();
+        std::fs::remove_file(&path).ok();
+
+        let names: Vec<&str> = config.targets.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["internal", "external"]);
+
+        let defaults = defaults();
+        let overrides = args.overrides();
+        assert_eq!(overrides.wait_time_seconds, Some(1));
+        assert_eq!(
+            defaults
+                .merge(&config.defaults)
+                .merge(&config.targets[0].runner)
+                .merge(&overrides),
+            RunnerConfig {
+                connect_timeout_ms: Some(DEFAULT_CONNECT_TIMEOUT_MS),
+                timeout_ms: Some(100),
+                wait_time_seconds: Some(1),
+                failure_threshold: Some(3),
+                recovery_threshold: Some(DEFAULT_RECOVERY_THRESHOLD),
+                concurrency: Some(DEFAULT_CONCURRENCY),
+                ..Default::default()
+            }
+        );
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[1].runner.wait_time_seconds, Some(1));
+        assert_eq!(
+            channels,
+            vec![
+                Channel::Webhook(Webhook::new("http://hooks:8080/file".to_string())),
+                Channel::Webhook(Webhook::new("http://hooks:8080/cli".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cli_run_wait_ms_conflicts_with_wait() {
+        let result = Cli::try_parse_from(["netcheck", "run", "--wait", "1", "--wait-ms", "250"]);
+        assert!(result.is_err());
+
+        let cli = Cli::parse_from(["netcheck", "run", "--wait-ms", "250"]);
+        let Commands::Run(args) = cli.command else {
+            panic!("expected the run command")
+        };
+        let runner = defaults().merge(&args.overrides());
+        assert_eq!(runner.wait_time_seconds, None);
+        assert_eq!(runner.wait_time_ms, Some(250));
+    }
+
+    #[test]
+    fn test_cli_check() {
+        let cli = Cli::parse_from(["netcheck", "check", "-n", "3", "--output", "json"]);
+        let Commands::Check(args) = cli.command else {
+            panic!("expected the check command")
+        };
+        assert_eq!(args.iterations, 3);
+        assert_eq!(args.output, Output::Json);
+
+        let runner = defaults().merge(&args.overrides());
+        assert_eq!(runner.failure_threshold, Some(3));
+        assert_eq!(runner.fan_out, Some(true));
+
+        let cli = Cli::parse_from([
+            "netcheck",
+            "check",
+            "--failure-threshold",
+            "1",
+            "--wait-ms",
+            "250",
+            "--quorum",
+            "all",
+        ]);
+        let Commands::Check(args) = cli.command else {
+            panic!("expected the check command")
+        };
+        let runner = defaults().merge(&args.overrides());
+        assert_eq!(runner.failure_threshold, Some(1));
+        assert_eq!(runner.wait_time_ms, Some(250));
+        assert_eq!(runner.quorum, Some(runner::Quorum::All));
+
+        assert!(Cli::try_parse_from(["netcheck", "check", "-n", "0"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_check_targets() {
+        let server = httpmock::MockServer::start();
+        server.mock(|when, then| {
+            when.path("/ok");
+            then.status(200);
+        });
+        server.mock(|when, then| {
+            when.path("/missing");
+            then.status(404);
+        });
+
+        let cli = Cli::parse_from([
+            "netcheck".to_string(),
+            "check".to_string(),
+            "--target".to_string(),
+            format!("up={}", server.url("/ok")),
+            "--target".to_string(),
+            format!("down={}", server.url("/missing")),
+        ]);
+        let Commands::Check(args) = cli.command else {
+            panic!("expected the check command")
+        };
+
+        let (reports, summary) = check_targets(&args).await.unwrap();
+        assert_eq!(
+            reports
+                .iter()
+                .map(|report| (report.name.as_str(), report.is_unavailable))
+                .collect::<Vec<_>>(),
+            vec![("down", true), ("up", false)]
+        );
+        let lines = summary.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("TARGET  STATUS"));
+        assert!(lines[1].starts_with("down    unavailable (0/1)"));
+        assert!(lines[1].ends_with("404"));
+        assert!(lines[2].starts_with("up      available (1/1)"));
+    }
+
+    #[test]
+    fn test_cli_target_errors() {
+        let error = Cli::try_parse_from(["netcheck", "run", "--target", "external"]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ValueValidation);
+        assert!(error
+            .to_string()
+            .contains("missing target name in \"external\", expected name=url[,url...]"));
+
+        let cli = Cli::parse_from([
+            "netcheck",
+            "check",
+            "--target",
+            "external=example.com",
+            "--target",
+            "external=example.org",
+        ]);
+        assert_eq!(
+            cli.validate(),
+            Err(runner::TargetParseError::DuplicateName {
+                name: "external".to_string()
             })
         );
     }
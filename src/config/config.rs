@@ -0,0 +1,1347 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::Method;
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::notifier::Channel;
+use crate::runner::{
+    self, BodyAssertion, Expectations, Quorum, RunnerBuilder, StatusRange, Strategy, Target,
+    TlsOptions, TlsVersion,
+};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("{source}")]
+    IO {
+        #[from]
+        source: std::io::Error,
+    },
+    #[error("{source}")]
+    YamlError {
+        #[from]
+        source: serde_yaml::Error,
+    },
+    #[error("{source}")]
+    TomlError {
+        #[from]
+        source: toml::de::Error,
+    },
+    #[error("unsupported config format {}, expected .yaml, .yml, .json or .toml", .path.display())]
+    UnsupportedFormat { path: PathBuf },
+    #[error("target {target}: {source}")]
+    InvalidTarget {
+        target: String,
+        source: runner::Error,
+    },
+    #[error("{source}")]
+    InvalidTargets {
+        #[from]
+        source: runner::TargetParseError,
+    },
+    #[error("target {target}: invalid header {header}")]
+    InvalidHeader { target: String, header: String },
+    #[error("target {target}: invalid method {method}")]
+    InvalidMethod { target: String, method: String },
+    #[error("target {target}: failed to read body from {}: {source}", .path.display())]
+    InvalidBody {
+        target: String,
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("target {target}: invalid regex {regex}: {source}")]
+    InvalidRegex {
+        target: String,
+        regex: String,
+        source: regex::Error,
+    },
+    #[error("target {target}: invalid tls {}: {reason}", .path.display())]
+    InvalidTls {
+        target: String,
+        path: PathBuf,
+        reason: String,
+    },
+    #[error("target {target}: invalid strategy, {reason}")]
+    InvalidStrategy { target: String, reason: String },
+    #[error("target {target}: quorum {quorum} needs between 1 and {urls} urls")]
+    InvalidQuorum {
+        target: String,
+        quorum: Quorum,
+        urls: usize,
+    },
+}
+
+/// Config is the declarative configuration of the targets to check and how to check them.
+///
+/// # Example
+///
+/// ```yaml
+/// defaults:
+///   timeout_ms: 500
+///   wait_time_seconds: 2
+/// targets:
+///   - name: external
+///     urls: ["https://one.one.one.one", "https://dns.google"]
+///   - name: database
+///     probe: tcp
+///     urls: ["postgres.db.svc.cluster.local:5432"]
+///     connect_timeout_ms: 100
+/// notifiers:
+///   - type: webhook
+///     url: https://hooks.example.com/netcheck
+/// ```
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub defaults: RunnerConfig,
+    #[serde(default)]
+    pub targets: Vec<TargetConfig>,
+    #[serde(default)]
+    pub notifiers: Vec<Channel>,
+}
+
+/// RunnerConfig holds the settings of a runner, unset values fall back to the next layer.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RunnerConfig {
+    pub connect_timeout_ms: Option<u64>,
+    pub timeout_ms: Option<u64>,
+    pub wait_time_seconds: Option<u64>,
+    pub wait_time_ms: Option<u64>,
+    pub jitter_ms: Option<u64>,
+    pub start_offset_ms: Option<u64>,
+    pub failure_threshold: Option<u8>,
+    pub recovery_threshold: Option<u8>,
+    pub strategy: Option<Strategy>,
+    pub quorum: Option<Quorum>,
+    pub fan_out: Option<bool>,
+    pub concurrency: Option<usize>,
+    /// Checks slower than this make the target degraded rather than available. There is no
+    /// separate threshold for the streaks: `failure_threshold` slow checks in a row make a url
+    /// degraded and `recovery_threshold` checks within the latency make it available again.
+    pub latency_threshold_ms: Option<u64>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// The method of http requests, e.g. `HEAD` or `POST`.
+    pub method: Option<String>,
+    pub body: Option<BodyConfig>,
+    pub expect: Option<ExpectConfig>,
+    pub tls: Option<TlsConfig>,
+}
+
+/// BodyConfig is the body sent with http requests, given inline or read from a file.
+///
+/// # Example
+///
+/// ```yaml
+/// body: '{"query": "{ health }"}'
+/// # or
+/// body:
+///   file: /etc/netcheck/health-query.json
+/// ```
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum BodyConfig {
+    Inline(String),
+    File { file: PathBuf },
+}
+
+/// ExpectConfig is what http responses have to match, by default any status below 400.
+///
+/// # Example
+///
+/// ```yaml
+/// expect:
+///   status: [2xx, 401, "300-302"]
+///   body:
+///     - contains: ok
+///     - regex: '"version": "2\.\d+'
+///     - json_pointer: /status
+///       equals: UP
+/// ```
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ExpectConfig {
+    #[serde(default)]
+    pub status: Vec<StatusRange>,
+    #[serde(default)]
+    pub body: Vec<AssertionConfig>,
+}
+
+/// AssertionConfig is a single assertion on the body of http responses.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum AssertionConfig {
+    Contains {
+        contains: String,
+    },
+    Regex {
+        regex: String,
+    },
+    JsonPointer {
+        json_pointer: String,
+        equals: serde_json::Value,
+    },
+}
+
+/// TlsConfig is the TLS settings of `https://` requests, for services signed by a private CA or
+/// requiring mutual TLS. The client certificate is given as a PEM certificate and PKCS#8 key, or
+/// as a PKCS#12 archive.
+///
+/// # Example
+///
+/// ```yaml
+/// tls:
+///   ca_files: [/etc/netcheck/internal-ca.pem]
+///   cert_file: /etc/netcheck/client.pem
+///   key_file: /etc/netcheck/client-key.pem
+///   server_name: api.internal.example.com
+///   min_version: "1.2"
+/// ```
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    /// PEM files of root certificates trusted in addition to the system roots.
+    #[serde(default)]
+    pub ca_files: Vec<PathBuf>,
+    pub cert_file: Option<PathBuf>,
+    pub key_file: Option<PathBuf>,
+    pub pkcs12_file: Option<PathBuf>,
+    #[serde(default)]
+    pub pkcs12_password: String,
+    /// The name sent as SNI and verified against the certificate instead of the url host.
+    pub server_name: Option<String>,
+    pub min_version: Option<TlsVersion>,
+    /// Accept any certificate, the connection is encrypted but not authenticated.
+    #[serde(default)]
+    pub insecure_skip_verify: bool,
+}
+
+/// TargetConfig is a single target and the settings used to check it.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(try_from = "TargetConfigFields")]
+pub struct TargetConfig {
+    pub name: String,
+    pub urls: Vec<String>,
+    pub probe: ProbeKind,
+    pub runner: RunnerConfig,
+}
+
+/// TargetConfigFields is a target as written in the file, `deny_unknown_fields` does not work
+/// together with `flatten` so the keys left over by the runner settings are collected and
+/// rejected instead.
+#[derive(Deserialize)]
+struct TargetConfigFields {
+    name: String,
+    urls: Vec<String>,
+    #[serde(default)]
+    probe: ProbeKind,
+    #[serde(flatten)]
+    runner: RunnerConfig,
+    #[serde(flatten)]
+    unknown: BTreeMap<String, IgnoredAny>,
+}
+
+impl TryFrom<TargetConfigFields> for TargetConfig {
+    type Error = String;
+
+    fn try_from(fields: TargetConfigFields) -> Result<Self, Self::Error> {
+        if let Some(field) = fields.unknown.keys().next() {
+            return Err(format!("target {}: unknown field `{}`", fields.name, field));
+        }
+
+        Ok(TargetConfig {
+            name: fields.name,
+            urls: fields.urls,
+            probe: fields.probe,
+            runner: fields.runner,
+        })
+    }
+}
+
+/// ProbeKind is how a target is checked, used as the scheme of urls given without one.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProbeKind {
+    Http,
+    #[default]
+    Https,
+    Tcp,
+    Dns,
+    Tls,
+}
+
+impl Config {
+    /// Load the config from a file, the format is picked from the file extension.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: Path to a `.yaml`, `.yml`, `.json` or `.toml` file.
+    ///
+    /// returns: Result<Config, Error>
+    pub fn from_file(path: &Path) -> Result<Config, Error> {
+        let contents = fs::read_to_string(path)?;
+
+        let config: Config = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml") | Some("yml") | Some("json") => serde_yaml::from_str(&contents)?,
+            Some("toml") => toml::from_str(&contents)?,
+            _ => {
+                return Err(Error::UnsupportedFormat {
+                    path: path.to_path_buf(),
+                })
+            }
+        };
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Check the urls and names of the targets, so an invalid file is rejected when it is loaded
+    /// rather than when its targets are run.
+    ///
+    /// returns: Result<(), Error>
+    pub fn validate(&self) -> Result<(), Error> {
+        let targets = self
+            .targets
+            .iter()
+            .map(TargetConfig::target)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(runner::validate_names(&targets)?)
+    }
+
+    /// Add targets to the config, replacing any existing targets with the same name.
+    ///
+    /// # Arguments
+    ///
+    /// * `targets`: The targets to add.
+    ///
+    /// returns: Config
+    pub fn with_targets(
+        mut self,
+        targets: Vec<Target>,
+    ) -> Config {
+        for target in targets {
+            let target = TargetConfig::from(target);
+            match self.targets.iter_mut().find(|t| t.name == target.name) {
+                Some(existing) => *existing = target,
+                None => self.targets.push(target),
+            }
+        }
+
+        self
+    }
+
+    /// Resolve the settings of every target, so each target holds every setting it runs with.
+    ///
+    /// Settings are layered so that `overrides` win over the target, which wins over the
+    /// config defaults, which win over `base`.
+    ///
+    /// # Arguments
+    ///
+    /// * `base`: The settings used when nothing else sets a value.
+    /// * `overrides`: The settings which take precedence over the config, e.g. CLI flags.
+    ///
+    /// returns: Vec<TargetConfig>
+    pub fn resolve(
+        &self,
+        base: &RunnerConfig,
+        overrides: &RunnerConfig,
+    ) -> Vec<TargetConfig> {
+        let defaults = base.merge(&self.defaults);
+
+        self.targets
+            .iter()
+            .map(|target| TargetConfig {
+                runner: defaults.merge(&target.runner).merge(overrides),
+                ..target.clone()
+            })
+            .collect()
+    }
+
+    /// Resolve the settings of every target into a RunnerBuilder.
+    ///
+    /// # Arguments
+    ///
+    /// * `base`: The settings used when nothing else sets a value.
+    /// * `overrides`: The settings which take precedence over the config, e.g. CLI flags.
+    ///
+    /// returns: Result<Vec<RunnerBuilder>, Error>
+    pub fn runner_builders(
+        &self,
+        base: &RunnerConfig,
+        overrides: &RunnerConfig,
+    ) -> Result<Vec<RunnerBuilder>, Error> {
+        self.resolve(base, overrides)
+            .iter()
+            .map(TargetConfig::runner_builder)
+            .collect()
+    }
+}
+
+impl RunnerConfig {
+    /// Merge two configs, values set in `other` take precedence.
+    ///
+    /// # Arguments
+    ///
+    /// * `other`: The config to layer on top.
+    ///
+    /// returns: RunnerConfig
+    pub fn merge(
+        &self,
+        other: &RunnerConfig,
+    ) -> RunnerConfig {
+        let mut headers = self.headers.clone();
+        headers.extend(other.headers.clone());
+
+        // The wait time can be given in seconds or milliseconds, so both are taken from the same
+        // layer to keep a lower layer's value from overriding a higher one.
+        let wait = if other.wait_time_seconds.is_some() || other.wait_time_ms.is_some() {
+            other
+        } else {
+            self
+        };
+
+        RunnerConfig {
+            connect_timeout_ms: other.connect_timeout_ms.or(self.connect_timeout_ms),
+            timeout_ms: other.timeout_ms.or(self.timeout_ms),
+            wait_time_seconds: wait.wait_time_seconds,
+            wait_time_ms: wait.wait_time_ms,
+            jitter_ms: other.jitter_ms.or(self.jitter_ms),
+            start_offset_ms: other.start_offset_ms.or(self.start_offset_ms),
+            failure_threshold: other.failure_threshold.or(self.failure_threshold),
+            recovery_threshold: other.recovery_threshold.or(self.recovery_threshold),
+            strategy: other.strategy.or(self.strategy),
+            quorum: other.quorum.or(self.quorum),
+            fan_out: other.fan_out.or(self.fan_out),
+            concurrency: other.concurrency.or(self.concurrency),
+            latency_threshold_ms: other.latency_threshold_ms.or(self.latency_threshold_ms),
+            headers,
+            method: other.method.clone().or(self.method.clone()),
+            body: other.body.clone().or(self.body.clone()),
+            expect: other.expect.clone().or(self.expect.clone()),
+            tls: other.tls.clone().or(self.tls.clone()),
+        }
+    }
+
+    fn apply(
+        &self,
+        target: &str,
+        mut builder: RunnerBuilder,
+    ) -> Result<RunnerBuilder, Error> {
+        if let Some(connect_timeout_ms) = self.connect_timeout_ms {
+            builder = builder.connect_timeout_ms(connect_timeout_ms);
+        }
+        if let Some(timeout_ms) = self.timeout_ms {
+            builder = builder.timeout_ms(timeout_ms);
+        }
+        if let Some(wait_time_seconds) = self.wait_time_seconds {
+            builder = builder.wait_time_seconds(wait_time_seconds);
+        }
+        if let Some(wait_time_ms) = self.wait_time_ms {
+            builder = builder.wait_time_ms(wait_time_ms);
+        }
+        if let Some(jitter_ms) = self.jitter_ms {
+            builder = builder.jitter_ms(jitter_ms);
+        }
+        if let Some(start_offset_ms) = self.start_offset_ms {
+            builder = builder.start_offset_ms(start_offset_ms);
+        }
+        if let Some(failure_threshold) = self.failure_threshold {
+            builder = builder.failure_threshold(failure_threshold);
+        }
+        if let Some(recovery_threshold) = self.recovery_threshold {
+            builder = builder.recovery_threshold(recovery_threshold);
+        }
+        if let Some(strategy) = self.strategy {
+            validate_strategy(target, &strategy)?;
+            builder = builder.strategy(strategy);
+        }
+        if let Some(quorum) = self.quorum {
+            builder = builder.quorum(quorum);
+        }
+        if let Some(fan_out) = self.fan_out {
+            builder = builder.fan_out(fan_out);
+        }
+        if let Some(concurrency) = self.concurrency {
+            builder = builder.concurrency(concurrency);
+        }
+        if let Some(latency_threshold_ms) = self.latency_threshold_ms {
+            builder = builder.latency_threshold_ms(latency_threshold_ms);
+        }
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let invalid = || Error::InvalidHeader {
+                target: target.to_string(),
+                header: name.clone(),
+            };
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?,
+                HeaderValue::from_str(value).map_err(|_| invalid())?,
+            );
+        }
+
+        if let Some(method) = &self.method {
+            builder = builder.method(
+                Method::from_bytes(method.to_uppercase().as_bytes()).map_err(|_| {
+                    Error::InvalidMethod {
+                        target: target.to_string(),
+                        method: method.clone(),
+                    }
+                })?,
+            );
+        }
+        match &self.body {
+            Some(BodyConfig::Inline(body)) => builder = builder.body(body.clone()),
+            Some(BodyConfig::File { file }) => {
+                let body = fs::read(file).map_err(|source| Error::InvalidBody {
+                    target: target.to_string(),
+                    path: file.clone(),
+                    source,
+                })?;
+                builder = builder.body(body);
+            }
+            None => {}
+        }
+        if let Some(expect) = &self.expect {
+            builder = builder.expectations(expect.expectations(target)?);
+        }
+        if let Some(tls) = &self.tls {
+            builder = builder.tls(tls.options(target)?);
+        }
+
+        Ok(builder.headers(headers))
+    }
+}
+
+impl ExpectConfig {
+    fn expectations(
+        &self,
+        target: &str,
+    ) -> Result<Expectations, Error> {
+        let body = self
+            .body
+            .iter()
+            .map(|assertion| {
+                Ok(match assertion {
+                    AssertionConfig::Contains { contains } => {
+                        BodyAssertion::Contains(contains.clone())
+                    }
+                    AssertionConfig::Regex { regex } => {
+                        BodyAssertion::Regex(regex::Regex::new(regex).map_err(|source| {
+                            Error::InvalidRegex {
+                                target: target.to_string(),
+                                regex: regex.clone(),
+                                source,
+                            }
+                        })?)
+                    }
+                    AssertionConfig::JsonPointer {
+                        json_pointer,
+                        equals,
+                    } => BodyAssertion::JsonPointer {
+                        pointer: json_pointer.clone(),
+                        equals: equals.clone(),
+                    },
+                })
+            })
+            .collect::<Result<Vec<BodyAssertion>, Error>>()?;
+
+        Ok(Expectations {
+            status: self.status.clone(),
+            body,
+        })
+    }
+}
+
+impl TlsConfig {
+    fn options(
+        &self,
+        target: &str,
+    ) -> Result<TlsOptions, Error> {
+        let invalid = |path: &PathBuf, reason: String| Error::InvalidTls {
+            target: target.to_string(),
+            path: path.clone(),
+            reason,
+        };
+        let read = |path: &PathBuf| fs::read(path).map_err(|err| invalid(path, err.to_string()));
+
+        let mut root_certificates = vec![];
+        for path in &self.ca_files {
+            root_certificates.extend(
+                runner::certificates_from_pem(&read(path)?)
+                    .map_err(|err| invalid(path, err.to_string()))?,
+            );
+        }
+
+        let identity = match (&self.cert_file, &self.key_file, &self.pkcs12_file) {
+            (None, None, None) => None,
+            (Some(cert), Some(key), None) => Some(
+                native_tls::Identity::from_pkcs8(&read(cert)?, &read(key)?)
+                    .map_err(|err| invalid(cert, err.to_string()))?,
+            ),
+            (None, None, Some(pkcs12)) => Some(
+                native_tls::Identity::from_pkcs12(&read(pkcs12)?, &self.pkcs12_password)
+                    .map_err(|err| invalid(pkcs12, err.to_string()))?,
+            ),
+            (Some(path), None, None) | (None, Some(path), None) => {
+                return Err(invalid(
+                    path,
+                    "cert_file and key_file must be set together".to_string(),
+                ))
+            }
+            (_, _, Some(path)) => {
+                return Err(invalid(
+                    path,
+                    "pkcs12_file can not be combined with cert_file and key_file".to_string(),
+                ))
+            }
+        };
+
+        Ok(TlsOptions {
+            root_certificates,
+            identity,
+            server_name: self.server_name.clone(),
+            min_version: self.min_version,
+            insecure_skip_verify: self.insecure_skip_verify,
+        })
+    }
+}
+
+fn validate_strategy(
+    target: &str,
+    strategy: &Strategy,
+) -> Result<(), Error> {
+    let invalid = |reason: &str| Error::InvalidStrategy {
+        target: target.to_string(),
+        reason: reason.to_string(),
+    };
+
+    match *strategy {
+        Strategy::Consecutive => Ok(()),
+        Strategy::Window {
+            checks, seconds, ..
+        } if checks.is_none() && seconds.is_none() => {
+            Err(invalid("window needs checks or seconds"))
+        }
+        Strategy::Window {
+            failure_percent, ..
+        } if !(0.0..=100.0).contains(&failure_percent) => {
+            Err(invalid("failure_percent must be between 0 and 100"))
+        }
+        Strategy::Window { .. } => Ok(()),
+    }
+}
+
+impl TargetConfig {
+    /// Parse the urls of the target, using the probe kind as the scheme of urls without one and
+    /// rejecting schemes no probe supports.
+    ///
+    /// returns: Result<Target, Error>
+    pub fn target(&self) -> Result<Target, Error> {
+        let urls = self
+            .urls
+            .iter()
+            .map(|url| runner::parse_url(url, self.probe.scheme()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|source| Error::InvalidTarget {
+                target: self.name.clone(),
+                source,
+            })?;
+        if let Some(url) = urls
+            .iter()
+            .find(|url| !runner::SUPPORTED_SCHEMES.contains(&url.scheme()))
+        {
+            return Err(Error::from(runner::TargetParseError::UnsupportedScheme {
+                name: self.name.clone(),
+                url: url.to_string(),
+                scheme: url.scheme().to_string(),
+            }));
+        }
+
+        Ok(Target::new(self.name.clone(), urls))
+    }
+
+    /// Create a RunnerBuilder for the target using its own settings.
+    ///
+    /// returns: Result<RunnerBuilder, Error>
+    pub fn runner_builder(&self) -> Result<RunnerBuilder, Error> {
+        if let Some(Quorum::Count(count)) = self.runner.quorum {
+            if count == 0 || count > self.urls.len() {
+                return Err(Error::InvalidQuorum {
+                    target: self.name.clone(),
+                    quorum: Quorum::Count(count),
+                    urls: self.urls.len(),
+                });
+            }
+        }
+
+        self.runner
+            .apply(&self.name, RunnerBuilder::new().target(self.target()?))
+    }
+}
+
+impl From<Target> for TargetConfig {
+    fn from(target: Target) -> Self {
+        TargetConfig {
+            name: target.name,
+            urls: target.urls.iter().map(|url| url.to_string()).collect(),
+            probe: ProbeKind::default(),
+            runner: RunnerConfig::default(),
+        }
+    }
+}
+
+impl ProbeKind {
+    /// The url scheme of the probe kind.
+    pub fn scheme(&self) -> &'static str {
+        match self {
+            ProbeKind::Http => "http",
+            ProbeKind::Https => runner::DEFAULT_SCHEME,
+            ProbeKind::Tcp => "tcp",
+            ProbeKind::Dns => "dns",
+            ProbeKind::Tls => "tls",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use pretty_assertions::assert_eq;
+    use reqwest::Url;
+
+    use super::*;
+    use crate::notifier::Webhook;
+
+    const YAML: &str = r#"
+defaults:
+  timeout_ms: 750
+  headers:
+    x-team: network
+targets:
+  - name: external
+    urls: ["one.one.one.one", "https://dns.google"]
+    wait_time_seconds: 10
+    quorum: any
+    method: HEAD
+    strategy:
+      window:
+        checks: 20
+        failure_percent: 30
+  - name: database
+    probe: tcp
+    urls: ["postgres.db.svc.cluster.local:5432"]
+    connect_timeout_ms: 100
+    failure_threshold: 2
+    headers:
+      x-team: storage
+notifiers:
+  - type: webhook
+    url: https://hooks.example.com/netcheck
+    retries: 5
+"#;
+
+    const TOML: &str = r#"
+[defaults]
+timeout_ms = 750
+
+[defaults.headers]
+x-team = "network"
+
+[[targets]]
+name = "external"
+urls = ["one.one.one.one", "https://dns.google"]
+wait_time_seconds = 10
+quorum = "any"
+method = "HEAD"
+
+[targets.strategy.window]
+checks = 20
+failure_percent = 30
+
+[[targets]]
+name = "database"
+probe = "tcp"
+urls = ["postgres.db.svc.cluster.local:5432"]
+connect_timeout_ms = 100
+failure_threshold = 2
+
+[targets.headers]
+x-team = "storage"
+
+[[notifiers]]
+type = "webhook"
+url = "https://hooks.example.com/netcheck"
+retries = 5
+"#;
+
+    fn write_config(
+        extension: &str,
+        contents: &str,
+    ) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "netcheck-config-{}-{}.{}",
+            std::process::id(),
+            rand::random::<u32>(),
+            extension
+        ));
+        let mut file = fs::File::create(&path).expect("failed to create config");
+        file.write_all(contents.as_bytes())
+            .expect("failed to write config");
+
+        path
+    }
+
+    fn expected() -> Config {
+        Config {
+            defaults: RunnerConfig {
+                timeout_ms: Some(750),
+                headers: BTreeMap::from([("x-team".to_string(), "network".to_string())]),
+                ..Default::default()
+            },
+            targets: vec![
+                TargetConfig {
+                    name: "external".to_string(),
+                    urls: vec![
+                        "one.one.one.one".to_string(),
+                        "https://dns.google".to_string(),
+                    ],
+                    probe: ProbeKind::Https,
+                    runner: RunnerConfig {
+                        wait_time_seconds: Some(10),
+                        quorum: Some(Quorum::Any),
+                        method: Some("HEAD".to_string()),
+                        strategy: Some(Strategy::Window {
+                            checks: Some(20),
+                            seconds: None,
+                            failure_percent: 30.0,
+                        }),
+                        ..Default::default()
+                    },
+                },
+                TargetConfig {
+                    name: "database".to_string(),
+                    urls: vec!["postgres.db.svc.cluster.local:5432".to_string()],
+                    probe: ProbeKind::Tcp,
+                    runner: RunnerConfig {
+                        connect_timeout_ms: Some(100),
+                        failure_threshold: Some(2),
+                        headers: BTreeMap::from([("x-team".to_string(), "storage".to_string())]),
+                        ..Default::default()
+                    },
+                },
+            ],
+            notifiers: vec![Channel::Webhook(Webhook {
+                retries: 5,
+                ..Webhook::new("https://hooks.example.com/netcheck".to_string())
+            })],
+        }
+    }
+
+    #[test]
+    fn test_config_from_file_yaml() {
+        let path = write_config("yaml", YAML);
+        let config = Config::from_file(&path).expect("failed to load config");
+        fs::remove_file(path).ok();
+
+        assert_eq!(config, expected());
+    }
+
+    #[test]
+    fn test_config_from_file_toml() {
+        let path = write_config("toml", TOML);
+        let config = Config::from_file(&path).expect("failed to load config");
+        fs::remove_file(path).ok();
+
+        assert_eq!(config, expected());
+    }
+
+    #[test]
+    fn test_config_from_file_unsupported() {
+        let path = write_config("ini", YAML);
+        let result = Config::from_file(&path);
+        fs::remove_file(path).ok();
+
+        match result {
+            Err(Error::UnsupportedFormat { .. }) => {}
+            other => panic!("Expected UnsupportedFormat, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_config_from_file_unknown_field() {
+        let path = write_config("yaml", "defaults:\n  retries: 3\n");
+        let result = Config::from_file(&path);
+        fs::remove_file(path).ok();
+
+        match result {
+            Err(Error::YamlError { .. }) => {}
+            other => panic!("Expected YamlError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_config_from_file_unknown_target_field() {
+        for (extension, contents) in [
+            (
+                "yaml",
+                "targets:\n  - name: external\n    urls: [one.one.one.one]\n    failure_treshold: 3\n",
+            ),
+            (
+                "toml",
+                "[[targets]]\nname = \"external\"\nurls = [\"one.one.one.one\"]\nfailure_treshold = 3\n",
+            ),
+        ] {
+            let path = write_config(extension, contents);
+            let result = Config::from_file(&path);
+            fs::remove_file(path).ok();
+
+            let err = result.expect_err(extension).to_string();
+            assert!(
+                err.contains("target external: unknown field `failure_treshold`"),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_from_file_invalid_targets() {
+        let path = write_config(
+            "yaml",
+            "targets:\n  - name: files\n    urls: [\"ftp://files.internal\"]\n",
+        );
+        let result = Config::from_file(&path);
+        fs::remove_file(path).ok();
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "target files: unsupported scheme ftp in ftp://files.internal/, expected one of http, \
+             https, tcp, dns, tls"
+        );
+
+        let path = write_config(
+            "yaml",
+            "targets:\n  - name: db\n    urls: [\"tcp://one:5432\"]\n  \
+             - name: db\n    urls: [\"tcp://two:5432\"]\n",
+        );
+        let result = Config::from_file(&path);
+        fs::remove_file(path).ok();
+        match result {
+            Err(Error::InvalidTargets {
+                source: runner::TargetParseError::DuplicateName { name },
+            }) => assert_eq!(name, "db"),
+            other => panic!("Expected DuplicateName, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_config_with_targets() {
+        let config = expected().with_targets(vec![
+            Target::new(
+                "database".to_string(),
+                vec![Url::parse("tcp://mysql:3306").unwrap()],
+            ),
+            Target::new(
+                "internal".to_string(),
+                vec![Url::parse("http://hello:9111").unwrap()],
+            ),
+        ]);
+
+        let names: Vec<&str> = config.targets.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["external", "database", "internal"]);
+        assert_eq!(config.targets[1].urls, vec!["tcp://mysql:3306".to_string()]);
+        assert_eq!(config.targets[1].runner, RunnerConfig::default());
+    }
+
+    #[test]
+    fn test_runner_config_merge() {
+        let base = RunnerConfig {
+            connect_timeout_ms: Some(500),
+            timeout_ms: Some(500),
+            headers: BTreeMap::from([
+                ("a".to_string(), "1".to_string()),
+                ("b".to_string(), "1".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let other = RunnerConfig {
+            timeout_ms: Some(100),
+            wait_time_seconds: Some(3),
+            headers: BTreeMap::from([("b".to_string(), "2".to_string())]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            base.merge(&other),
+            RunnerConfig {
+                connect_timeout_ms: Some(500),
+                timeout_ms: Some(100),
+                wait_time_seconds: Some(3),
+                headers: BTreeMap::from([
+                    ("a".to_string(), "1".to_string()),
+                    ("b".to_string(), "2".to_string()),
+                ]),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_runner_config_merge_wait_time() {
+        let seconds = RunnerConfig {
+            wait_time_seconds: Some(2),
+            ..Default::default()
+        };
+        let millis = RunnerConfig {
+            wait_time_ms: Some(250),
+            jitter_ms: Some(50),
+            ..Default::default()
+        };
+
+        let merged = seconds.merge(&millis);
+        assert_eq!(merged.wait_time_seconds, None);
+        assert_eq!(merged.wait_time_ms, Some(250));
+        assert_eq!(merged.jitter_ms, Some(50));
+
+        let merged = millis.merge(&seconds);
+        assert_eq!(merged.wait_time_seconds, Some(2));
+        assert_eq!(merged.wait_time_ms, None);
+        assert_eq!(merged.jitter_ms, Some(50));
+    }
+
+    #[test]
+    fn test_target_config_target() {
+        let target = expected().targets[1]
+            .target()
+            .expect("failed to parse target");
+
+        assert_eq!(target.name, "database");
+        assert_eq!(
+            target.urls,
+            vec![Url::parse("tcp://postgres.db.svc.cluster.local:5432").unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_target_config_target_invalid_url() {
+        let target = TargetConfig {
+            name: "broken".to_string(),
+            urls: vec!["http://".to_string()],
+            probe: ProbeKind::Https,
+            runner: RunnerConfig::default(),
+        };
+
+        match target.target() {
+            Err(Error::InvalidTarget { target, .. }) => assert_eq!(target, "broken"),
+            other => panic!("Expected InvalidTarget, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_config_resolve() {
+        let base = RunnerConfig {
+            connect_timeout_ms: Some(500),
+            ..Default::default()
+        };
+        let overrides = RunnerConfig {
+            wait_time_seconds: Some(1),
+            ..Default::default()
+        };
+
+        let targets = expected().resolve(&base, &overrides);
+        assert_eq!(
+            targets[0].runner,
+            RunnerConfig {
+                connect_timeout_ms: Some(500),
+                timeout_ms: Some(750),
+                wait_time_seconds: Some(1),
+                strategy: expected().targets[0].runner.strategy,
+                quorum: Some(Quorum::Any),
+                headers: BTreeMap::from([("x-team".to_string(), "network".to_string())]),
+                method: Some("HEAD".to_string()),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            targets[1].runner,
+            RunnerConfig {
+                connect_timeout_ms: Some(100),
+                timeout_ms: Some(750),
+                wait_time_seconds: Some(1),
+                failure_threshold: Some(2),
+                headers: BTreeMap::from([("x-team".to_string(), "storage".to_string())]),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_config_runner_builders() {
+        let base = RunnerConfig {
+            connect_timeout_ms: Some(500),
+            timeout_ms: Some(500),
+            wait_time_seconds: Some(2),
+            failure_threshold: Some(5),
+            recovery_threshold: Some(10),
+            fan_out: Some(true),
+            concurrency: Some(4),
+            latency_threshold_ms: Some(2000),
+            ..Default::default()
+        };
+        let overrides = RunnerConfig {
+            failure_threshold: Some(9),
+            ..Default::default()
+        };
+
+        let builders = expected()
+            .runner_builders(&base, &overrides)
+            .expect("failed to build runners");
+        assert_eq!(builders.len(), 2);
+
+        let runners: Vec<_> = builders.into_iter().map(|b| b.build()).collect();
+        assert_eq!(runners[0].failure_threshold, 9);
+        assert_eq!(runners[1].failure_threshold, 9);
+        assert_eq!(runners[0].recovery_threshold, 10);
+        assert_eq!(runners[1].recovery_threshold, 10);
+        assert_eq!(runners[0].fan_out, true);
+        assert_eq!(runners[1].concurrency, 4);
+        assert_eq!(runners[0].latency_threshold_ms, Some(2000));
+    }
+
+    #[test]
+    fn test_config_runner_builders_invalid_header() {
+        let mut config = expected();
+        config
+            .defaults
+            .headers
+            .insert("bad header".to_string(), "value".to_string());
+
+        match config.runner_builders(&RunnerConfig::default(), &RunnerConfig::default()) {
+            Err(Error::InvalidHeader { header, .. }) => assert_eq!(header, "bad header"),
+            Err(other) => panic!("Expected InvalidHeader, got {:?}", other),
+            Ok(_) => panic!("Expected InvalidHeader"),
+        }
+    }
+
+    #[test]
+    fn test_config_runner_builders_method_body() {
+        let path = write_config("json", r#"{"query": "{ health }"}"#);
+        let mut config = expected();
+        config.defaults.body = Some(BodyConfig::File { file: path.clone() });
+        config.targets[1].runner.method = Some("post".to_string());
+        assert!(config
+            .runner_builders(&RunnerConfig::default(), &RunnerConfig::default())
+            .is_ok());
+        fs::remove_file(&path).expect("failed to remove config");
+
+        match config.runner_builders(&RunnerConfig::default(), &RunnerConfig::default()) {
+            Err(err @ Error::InvalidBody { .. }) => assert!(err.to_string().starts_with(&format!(
+                "target external: failed to read body from {}",
+                path.display()
+            ))),
+            Err(other) => panic!("Expected InvalidBody, got {:?}", other),
+            Ok(_) => panic!("Expected InvalidBody"),
+        }
+
+        config.defaults.body = Some(BodyConfig::Inline("ping".to_string()));
+        config.targets[1].runner.method = Some("not a method".to_string());
+        match config.runner_builders(&RunnerConfig::default(), &RunnerConfig::default()) {
+            Err(Error::InvalidMethod { target, method }) => {
+                assert_eq!(target, "database");
+                assert_eq!(method, "not a method");
+            }
+            Err(other) => panic!("Expected InvalidMethod, got {:?}", other),
+            Ok(_) => panic!("Expected InvalidMethod"),
+        }
+    }
+
+    #[test]
+    fn test_config_body_deserialize() {
+        let runner: RunnerConfig = serde_yaml::from_str("body: ping").unwrap();
+        assert_eq!(runner.body, Some(BodyConfig::Inline("ping".to_string())));
+
+        let runner: RunnerConfig = serde_yaml::from_str("body: {file: /tmp/body.json}").unwrap();
+        assert_eq!(
+            runner.body,
+            Some(BodyConfig::File {
+                file: PathBuf::from("/tmp/body.json")
+            })
+        );
+    }
+
+    #[test]
+    fn test_config_expect_deserialize() {
+        let runner: RunnerConfig = serde_yaml::from_str(
+            r#"
+expect:
+  status: [401, 2xx]
+  body:
+    - contains: ok
+    - regex: 'v\d+'
+    - json_pointer: /status
+      equals: UP
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            runner.expect,
+            Some(ExpectConfig {
+                status: vec!["401".parse().unwrap(), "2xx".parse().unwrap()],
+                body: vec![
+                    AssertionConfig::Contains {
+                        contains: "ok".to_string()
+                    },
+                    AssertionConfig::Regex {
+                        regex: "v\\d+".to_string()
+                    },
+                    AssertionConfig::JsonPointer {
+                        json_pointer: "/status".to_string(),
+                        equals: serde_json::json!("UP"),
+                    },
+                ],
+            })
+        );
+
+        assert!(serde_yaml::from_str::<RunnerConfig>("expect: {status: [600]}").is_err());
+        assert!(
+            serde_yaml::from_str::<RunnerConfig>("expect: {body: [{json_pointer: /a}]}").is_err()
+        );
+    }
+
+    #[test]
+    fn test_config_runner_builders_invalid_regex() {
+        let mut config = expected();
+        config.targets[0].runner.expect = Some(ExpectConfig {
+            status: vec![],
+            body: vec![AssertionConfig::Regex {
+                regex: "(unclosed".to_string(),
+            }],
+        });
+
+        match config.runner_builders(&RunnerConfig::default(), &RunnerConfig::default()) {
+            Err(Error::InvalidRegex { target, regex, .. }) => {
+                assert_eq!(target, "external");
+                assert_eq!(regex, "(unclosed");
+            }
+            Err(other) => panic!("Expected InvalidRegex, got {:?}", other),
+            Ok(_) => panic!("Expected InvalidRegex"),
+        }
+    }
+
+    #[test]
+    fn test_config_tls() {
+        let ca = rcgen::Certificate::from_params(rcgen::CertificateParams::new(vec![])).unwrap();
+        let client = rcgen::Certificate::from_params(rcgen::CertificateParams::new(vec![
+            "netcheck".to_string(),
+        ]))
+        .unwrap();
+        let ca_file = write_config("pem", &ca.serialize_pem().unwrap());
+        let cert_file = write_config("pem", &client.serialize_pem_with_signer(&ca).unwrap());
+        let key_file = write_config("pem", &client.serialize_private_key_pem());
+
+        let runner: RunnerConfig = serde_yaml::from_str(&format!(
+            "tls: {{ca_files: [{}], cert_file: {}, key_file: {}, server_name: api.internal, \
+             min_version: 1.2}}",
+            ca_file.display(),
+            cert_file.display(),
+            key_file.display()
+        ))
+        .unwrap();
+        let tls = runner.tls.clone().unwrap();
+        assert_eq!(tls.min_version, Some(TlsVersion::Tls12));
+        assert_eq!(tls.insecure_skip_verify, false);
+
+        let options = tls.options("api").unwrap();
+        assert_eq!(options.root_certificates.len(), 1);
+        assert!(options.identity.is_some());
+        assert_eq!(options.server_name, Some("api.internal".to_string()));
+        assert!(options.connector().is_ok());
+
+        let mut config = expected();
+        config.targets[0].runner.tls = Some(TlsConfig {
+            key_file: None,
+            ..tls.clone()
+        });
+        match config.runner_builders(&RunnerConfig::default(), &RunnerConfig::default()) {
+            Err(Error::InvalidTls {
+                target,
+                path,
+                reason,
+            }) => {
+                assert_eq!(target, "external");
+                assert_eq!(path, cert_file);
+                assert_eq!(reason, "cert_file and key_file must be set together");
+            }
+            Err(other) => panic!("Expected InvalidTls, got {:?}", other),
+            Ok(_) => panic!("Expected InvalidTls"),
+        }
+
+        for path in [&ca_file, &cert_file, &key_file] {
+            fs::remove_file(path).expect("failed to remove pem");
+        }
+        match tls.options("api") {
+            Err(Error::InvalidTls { path, .. }) => assert_eq!(path, ca_file),
+            Err(other) => panic!("Expected InvalidTls, got {:?}", other),
+            Ok(_) => panic!("Expected InvalidTls"),
+        }
+    }
+
+    #[test]
+    fn test_config_runner_builders_invalid_strategy() {
+        let mut config = expected();
+        config.targets[0].runner.strategy = Some(Strategy::Window {
+            checks: None,
+            seconds: None,
+            failure_percent: 30.0,
+        });
+
+        match config.runner_builders(&RunnerConfig::default(), &RunnerConfig::default()) {
+            Err(Error::InvalidStrategy { target, reason }) => {
+                assert_eq!(target, "external");
+                assert_eq!(reason, "window needs checks or seconds");
+            }
+            Err(other) => panic!("Expected InvalidStrategy, got {:?}", other),
+            Ok(_) => panic!("Expected InvalidStrategy"),
+        }
+
+        config.targets[0].runner.strategy = Some(Strategy::Window {
+            checks: Some(10),
+            seconds: None,
+            failure_percent: 120.0,
+        });
+        match config.runner_builders(&RunnerConfig::default(), &RunnerConfig::default()) {
+            Err(Error::InvalidStrategy { reason, .. }) => {
+                assert_eq!(reason, "failure_percent must be between 0 and 100")
+            }
+            Err(other) => panic!("Expected InvalidStrategy, got {:?}", other),
+            Ok(_) => panic!("Expected InvalidStrategy"),
+        }
+    }
+
+    #[test]
+    fn test_target_config_runner_builder_invalid_quorum() {
+        let mut target = expected().targets[0].clone();
+        target.runner.quorum = Some(Quorum::Count(3));
+
+        match target.runner_builder() {
+            Err(err @ Error::InvalidQuorum { .. }) => assert_eq!(
+                err.to_string(),
+                "target external: quorum 3 needs between 1 and 2 urls"
+            ),
+            Err(other) => panic!("Expected InvalidQuorum, got {:?}", other),
+            Ok(_) => panic!("Expected InvalidQuorum"),
+        }
+
+        target.runner.quorum = Some(Quorum::Count(2));
+        let runner = target.runner_builder().unwrap().build();
+        assert_eq!(runner.quorum, Quorum::Count(2));
+    }
+}
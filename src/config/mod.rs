@@ -0,0 +1,9 @@
+pub use self::config::{
+    AssertionConfig, BodyConfig, Config, Error, ExpectConfig, ProbeKind, RunnerConfig,
+    TargetConfig, TlsConfig,
+};
+pub use self::watcher::Watcher;
+
+#[allow(clippy::module_inception)]
+mod config;
+mod watcher;
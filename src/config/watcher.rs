@@ -0,0 +1,124 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tokio::signal::unix::{signal, Signal, SignalKind};
+use tokio::time::{self, Interval, MissedTickBehavior};
+use tracing::{debug, info};
+
+use crate::config::Error;
+
+/// Watcher waits for a config file to change, either on disk or when SIGHUP is received.
+///
+/// Changes are detected by comparing the contents of the file, so the atomic symlink swaps used
+/// for Kubernetes ConfigMap volumes are picked up as well as in place edits.
+pub struct Watcher {
+    path: PathBuf,
+    contents: Option<Vec<u8>>,
+    interval: Interval,
+    hangup: Signal,
+}
+
+impl Watcher {
+    /// Create a new Watcher, remembering the current contents of the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: Path of the config file to watch.
+    /// * `poll_interval`: How often the file is checked for changes.
+    ///
+    /// returns: Result<Watcher, Error>
+    pub async fn new(
+        path: &Path,
+        poll_interval: Duration,
+    ) -> Result<Watcher, Error> {
+        let mut interval = time::interval(poll_interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        Ok(Watcher {
+            path: path.to_path_buf(),
+            contents: tokio::fs::read(path).await.ok(),
+            interval,
+            hangup: signal(SignalKind::hangup())?,
+        })
+    }
+
+    /// Wait until the file contents change or SIGHUP is received.
+    #[tracing::instrument(level = "debug", skip(self), fields(path = %self.path.display()))]
+    pub async fn changed(&mut self) {
+        loop {
+            tokio::select! {
+                _ = self.interval.tick() => {
+                    let contents = tokio::fs::read(&self.path).await.ok();
+                    if contents.is_some() && contents != self.contents {
+                        info!(path = %self.path.display(), "config file changed");
+                        self.contents = contents;
+                        return;
+                    }
+                    debug!(path = %self.path.display(), "config file unchanged");
+                }
+                _ = self.hangup.recv() => {
+                    info!(path = %self.path.display(), "received SIGHUP");
+                    self.contents = tokio::fs::read(&self.path).await.ok();
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_path() -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "netcheck-watcher-{}-{}.yaml",
+            std::process::id(),
+            rand::random::<u32>()
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_watcher_changed() {
+        let path = config_path();
+        std::fs::write(&path, "targets: []\n").unwrap();
+        let mut watcher = Watcher::new(&path, Duration::from_millis(10))
+            .await
+            .unwrap();
+
+        std::fs::write(&path, "targets: [{name: a, urls: [example.com]}]\n").unwrap();
+        let changed = time::timeout(Duration::from_secs(1), watcher.changed()).await;
+        std::fs::remove_file(&path).ok();
+
+        assert!(changed.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_watcher_unchanged() {
+        let path = config_path();
+        std::fs::write(&path, "targets: []\n").unwrap();
+        let mut watcher = Watcher::new(&path, Duration::from_millis(10))
+            .await
+            .unwrap();
+
+        std::fs::write(&path, "targets: []\n").unwrap();
+        let changed = time::timeout(Duration::from_millis(100), watcher.changed()).await;
+        std::fs::remove_file(&path).ok();
+
+        assert!(changed.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_watcher_missing_file_ignored() {
+        let path = config_path();
+        std::fs::write(&path, "targets: []\n").unwrap();
+        let mut watcher = Watcher::new(&path, Duration::from_millis(10))
+            .await
+            .unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+        let changed = time::timeout(Duration::from_millis(100), watcher.changed()).await;
+
+        assert!(changed.is_err());
+    }
+}
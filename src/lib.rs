@@ -5,6 +5,9 @@ pub mod built_info {
     include!(concat!(env!("OUT_DIR"), "/built.rs"));
 }
 
+pub mod config;
 pub mod log;
 pub mod metric;
+pub mod notifier;
 pub mod runner;
+pub mod supervisor;
@@ -1,6 +1,7 @@
 use tracing_subscriber::filter::LevelFilter;
 use tracing_subscriber::fmt::format::FmtSpan;
 use tracing_subscriber::fmt::time::UtcTime;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::{EnvFilter, Layer};
@@ -14,6 +15,7 @@ pub struct Builder {
     span_list: bool,
     thread_names: bool,
     thread_ids: bool,
+    stderr: bool,
 }
 
 impl Builder {
@@ -84,6 +86,14 @@ impl Builder {
         self
     }
 
+    pub fn with_stderr(
+        &mut self,
+        stderr: bool,
+    ) -> &mut Self {
+        self.stderr = stderr;
+        self
+    }
+
     pub fn build(&mut self) {
         let env = EnvFilter::builder()
             .with_default_directive(self.level.into())
@@ -109,6 +119,11 @@ impl Builder {
                 FmtSpan::NONE
             })
             .with_thread_names(self.thread_names)
+            .with_writer(if self.stderr {
+                BoxMakeWriter::new(std::io::stderr)
+            } else {
+                BoxMakeWriter::new(std::io::stdout)
+            })
             .log_internal_errors(true)
             .with_filter(env);
 
@@ -127,6 +142,7 @@ impl Default for Builder {
             target: true,
             flatten_event: true,
             file: true,
+            stderr: false,
         }
     }
 }
@@ -156,7 +172,8 @@ mod tests {
             .with_target(false)
             .with_span_list(false)
             .with_thread_names(false)
-            .with_thread_ids(false);
+            .with_thread_ids(false)
+            .with_stderr(true);
 
         builder.build();
 
@@ -168,5 +185,6 @@ mod tests {
         assert_eq!(builder.span_list, false);
         assert_eq!(builder.thread_names, false);
         assert_eq!(builder.thread_ids, false);
+        assert_eq!(builder.stderr, true);
     }
 }
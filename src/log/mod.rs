@@ -1,4 +1,5 @@
 pub use self::log::Builder;
 pub use self::log::Logger;
 
+#[allow(clippy::module_inception)]
 mod log;
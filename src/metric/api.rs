@@ -0,0 +1,171 @@
+use std::sync::{Arc, Mutex};
+
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+
+use crate::runner::{Registry, TargetReport};
+use crate::supervisor::{Health, Supervisor};
+
+pub const PATH_STATUS: &str = "/api/v1/status";
+pub const PATH_TARGET: &str = "/api/v1/targets/{name}";
+pub const PATH_HEALTHZ: &str = "/healthz";
+pub const PATH_READYZ: &str = "/readyz";
+
+#[derive(Serialize)]
+struct StatusResponse {
+    targets: Vec<TargetReport>,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// Serve the status of every target.
+pub async fn status(registry: web::Data<Registry>) -> HttpResponse {
+    HttpResponse::Ok().json(StatusResponse {
+        targets: registry.targets(),
+    })
+}
+
+/// Serve the status of a single target, or 404 if it is not running.
+pub async fn target(
+    registry: web::Data<Registry>,
+    name: web::Path<String>,
+) -> HttpResponse {
+    match registry.target(&name) {
+        Some(report) => HttpResponse::Ok().json(report),
+        None => HttpResponse::NotFound().json(ErrorResponse {
+            error: format!("target {} not found", name),
+        }),
+    }
+}
+
+/// Serve 200 while every runner task is alive, 503 once one has died.
+pub async fn healthz(supervisor: web::Data<Arc<Mutex<Supervisor>>>) -> HttpResponse {
+    let health = health(&supervisor);
+    respond(health.is_live(), health)
+}
+
+/// Serve 200 once every runner task is alive and has reported a result, 503 otherwise.
+pub async fn readyz(supervisor: web::Data<Arc<Mutex<Supervisor>>>) -> HttpResponse {
+    let health = health(&supervisor);
+    respond(health.is_ready(), health)
+}
+
+fn health(supervisor: &Mutex<Supervisor>) -> Health {
+    supervisor
+        .lock()
+        .expect("Failed to lock the supervisor")
+        .health()
+}
+
+fn respond(
+    ok: bool,
+    health: Health,
+) -> HttpResponse {
+    if ok {
+        HttpResponse::Ok().json(health)
+    } else {
+        HttpResponse::ServiceUnavailable().json(health)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::body::to_bytes;
+    use actix_web::http::StatusCode;
+    use pretty_assertions::assert_eq;
+    use reqwest::Url;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::config::{ProbeKind, RunnerConfig, TargetConfig};
+    use crate::runner::{Quorum, Status, Target, TargetStatus};
+
+    fn registry() -> Registry {
+        let registry = Registry::new();
+        let target = Target::new(
+            "external".to_string(),
+            vec![Url::parse("https://example.com").unwrap()],
+        );
+        let status = TargetStatus::new(Quorum::Any, vec![Status::new(1, 1)]);
+        registry.update(TargetReport::new(&target, &status));
+
+        registry
+    }
+
+    async fn body(resp: HttpResponse) -> Value {
+        let bytes = to_bytes(resp.into_body()).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_api_status() {
+        let resp = status(web::Data::new(registry())).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let body = body(resp).await;
+        assert_eq!(body["targets"][0]["name"], "external");
+        assert_eq!(body["targets"][0]["is_unavailable"], false);
+        assert_eq!(body["targets"][0]["urls"][0]["url"], "https://example.com/");
+        assert_eq!(body["targets"][0]["urls"][0]["last_error"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_api_target() {
+        let resp = target(
+            web::Data::new(registry()),
+            web::Path::from("external".to_string()),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(body(resp).await["quorum"], "any");
+    }
+
+    #[tokio::test]
+    async fn test_api_target_not_found() {
+        let resp = target(
+            web::Data::new(registry()),
+            web::Path::from("internal".to_string()),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(body(resp).await["error"], "target internal not found");
+    }
+
+    #[tokio::test]
+    async fn test_api_healthz_readyz() {
+        let registry = Registry::new();
+        let supervisor = Arc::new(Mutex::new(Supervisor::with_registry(registry.clone())));
+        // The server never answers, so the runner stays pending until it is aborted.
+        let silent = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        supervisor
+            .lock()
+            .unwrap()
+            .reconcile(vec![TargetConfig {
+                name: "database".to_string(),
+                urls: vec![format!("http://{}", silent.local_addr().unwrap())],
+                probe: ProbeKind::Http,
+                runner: RunnerConfig {
+                    timeout_ms: Some(60_000),
+                    ..Default::default()
+                },
+            }])
+            .unwrap();
+
+        let resp = healthz(web::Data::new(supervisor.clone())).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = readyz(web::Data::new(supervisor.clone())).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body(resp).await["pending"][0], "database");
+
+        let status = TargetStatus::new(Quorum::Any, vec![Status::new(1, 1)]);
+        let target = Target::new("database".to_string(), vec![]);
+        registry.update(TargetReport::new(&target, &status));
+        let resp = readyz(web::Data::new(supervisor.clone())).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        supervisor.lock().unwrap().abort();
+    }
+}
@@ -1,4 +1,5 @@
 use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::{Arc, Mutex};
 
 use actix_web::{web, App, HttpServer};
 use actix_web_opentelemetry::{PrometheusMetricsHandler, RequestMetrics};
@@ -13,9 +14,13 @@ use opentelemetry_semantic_conventions::{
 };
 use thiserror::Error;
 use tokio::task;
+use tokio_util::sync::CancellationToken;
 use tracing::info;
 
 use crate::built_info;
+use crate::metric::api;
+use crate::runner::Registry;
+use crate::supervisor::Supervisor;
 
 const DEFAULT_PORT: u16 = 8080;
 
@@ -49,6 +54,7 @@ pub enum Error {
 pub struct MetricProvider {
     pub metrics_handler: PrometheusMetricsHandler,
     pub meter_provider: SdkMeterProvider,
+    pub registry: Registry,
 }
 
 impl MetricProvider {
@@ -74,30 +80,67 @@ impl MetricProvider {
         Self {
             metrics_handler,
             meter_provider,
+            registry: Registry::new(),
         }
     }
 
-    #[tracing::instrument(level = "debug")]
+    /// Serve the metrics and status api until the shutdown token is cancelled, finishing the
+    /// requests in flight before returning.
+    ///
+    /// # Arguments
+    ///
+    /// * `port`: The port to listen on, defaults to 8080.
+    /// * `supervisor`: The supervisor the status api reports on.
+    /// * `shutdown`: Cancelled to stop the server.
+    ///
+    /// returns: Result<(), Error>
+    #[tracing::instrument(level = "debug", skip(shutdown))]
     pub async fn listen(
         &self,
         port: Option<u16>,
+        supervisor: Arc<Mutex<Supervisor>>,
+        shutdown: CancellationToken,
     ) -> Result<(), Error> {
         let port = port.unwrap_or(DEFAULT_PORT);
-        let meter_provider = self.meter_provider.clone();
         let metrics_handler = self.metrics_handler.clone();
+        let registry = web::Data::new(self.registry.clone());
+        let supervisor = web::Data::new(supervisor);
         let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), port));
 
-        HttpServer::new(move || {
-            let app = App::new().wrap(RequestMetrics::default());
-            let app = app.route("/metrics", web::get().to(metrics_handler.clone()));
-            app
+        let server = HttpServer::new(move || {
+            App::new()
+                .wrap(RequestMetrics::default())
+                .app_data(registry.clone())
+                .app_data(supervisor.clone())
+                .route("/metrics", web::get().to(metrics_handler.clone()))
+                .route(api::PATH_STATUS, web::get().to(api::status))
+                .route(api::PATH_TARGET, web::get().to(api::target))
+                .route(api::PATH_HEALTHZ, web::get().to(api::healthz))
+                .route(api::PATH_READYZ, web::get().to(api::readyz))
         })
+        .disable_signals()
         .bind(addr)?
-        .run()
-        .await?;
+        .run();
+
+        let handle = server.handle();
+        tokio::spawn(async move {
+            shutdown.cancelled().await;
+            handle.stop(true).await;
+        });
 
         info!("metrics server listening on port {}", port);
-        meter_provider.shutdown()?;
+        server.await?;
+        info!("metrics server stopped");
+
+        Ok(())
+    }
+
+    /// Flush and stop the meter provider, called once the runners have stopped.
+    ///
+    /// returns: Result<(), Error>
+    pub fn shutdown(&self) -> Result<(), Error> {
+        self.meter_provider.force_flush()?;
+        self.meter_provider.shutdown()?;
 
         Ok(())
     }
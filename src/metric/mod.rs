@@ -1,4 +1,6 @@
 pub use self::metric::Error;
 pub use self::metric::MetricProvider;
 
+mod api;
+#[allow(clippy::module_inception)]
 mod metric;
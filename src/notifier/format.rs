@@ -0,0 +1,285 @@
+use serde_json::{json, Value};
+
+use crate::notifier::{Notification, Webhook};
+use crate::runner::State;
+
+pub const DEFAULT_UNAVAILABLE_TEMPLATE: &str = "{target} is unavailable, \
+    {available_urls}/{total_urls} urls available, last error from {url}: {last_error}";
+pub const DEFAULT_AVAILABLE_TEMPLATE: &str =
+    "{target} is available again after {outage}, {available_urls}/{total_urls} urls available";
+pub const DEFAULT_DEGRADED_TEMPLATE: &str = "{target} is degraded, \
+    {available_urls}/{total_urls} urls available, slowest {url} took {latency}";
+
+/// Render the text of a notification from the template for its event, falling back to the
+/// default templates.
+///
+/// The placeholders `{target}`, `{event}`, `{status}`, `{url}`, `{last_error}`, `{latency}`,
+/// `{outage}`, `{available_urls}`, `{total_urls}` and `{timestamp}` are replaced with the values
+/// of the notification, missing values are rendered as `-`.
+///
+/// # Arguments
+///
+/// * `notification`: The notification to render.
+/// * `unavailable_template`: The template used when the target becomes unavailable.
+/// * `available_template`: The template used when the target becomes available.
+/// * `degraded_template`: The template used when the target becomes degraded.
+///
+/// returns: String
+pub fn render(
+    notification: &Notification,
+    unavailable_template: Option<&str>,
+    available_template: Option<&str>,
+    degraded_template: Option<&str>,
+) -> String {
+    let status = notification.event.state();
+    let template = match status {
+        State::Unavailable => unavailable_template.unwrap_or(DEFAULT_UNAVAILABLE_TEMPLATE),
+        State::Available => available_template.unwrap_or(DEFAULT_AVAILABLE_TEMPLATE),
+        State::Degraded => degraded_template.unwrap_or(DEFAULT_DEGRADED_TEMPLATE),
+    };
+
+    template
+        .replace("{target}", &notification.target)
+        .replace("{event}", notification.event.name())
+        .replace("{status}", &status.to_string())
+        .replace("{url}", notification.url.as_deref().unwrap_or("-"))
+        .replace(
+            "{last_error}",
+            notification.last_error.as_deref().unwrap_or("-"),
+        )
+        .replace(
+            "{latency}",
+            &notification
+                .latency_ms
+                .map(|latency| format!("{:.0}ms", latency))
+                .unwrap_or_else(|| "-".to_string()),
+        )
+        .replace(
+            "{outage}",
+            &notification
+                .outage_seconds
+                .map(format_duration)
+                .unwrap_or_else(|| "-".to_string()),
+        )
+        .replace("{available_urls}", &notification.available_urls.to_string())
+        .replace("{total_urls}", &notification.total_urls.to_string())
+        .replace("{timestamp}", &notification.timestamp.to_rfc3339())
+}
+
+/// Build a Slack incoming webhook payload for the notification.
+pub fn slack(
+    notification: &Notification,
+    webhook: &Webhook,
+) -> Value {
+    let emoji = match notification.event.state() {
+        State::Unavailable => ":red_circle:",
+        State::Degraded => ":large_yellow_circle:",
+        State::Available => ":large_green_circle:",
+    };
+
+    json!({ "text": format!("{} {}", emoji, render_webhook(notification, webhook)) })
+}
+
+/// Build a Microsoft Teams workflow payload holding an Adaptive Card for the notification.
+pub fn teams(
+    notification: &Notification,
+    webhook: &Webhook,
+) -> Value {
+    let status = notification.event.state();
+    let title = format!("{} is {}", notification.target, status);
+    let color = match status {
+        State::Unavailable => "Attention",
+        State::Degraded => "Warning",
+        State::Available => "Good",
+    };
+
+    json!({
+        "type": "message",
+        "attachments": [{
+            "contentType": "application/vnd.microsoft.card.adaptive",
+            "content": {
+                "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                "type": "AdaptiveCard",
+                "version": "1.4",
+                "body": [
+                    {
+                        "type": "TextBlock",
+                        "text": title,
+                        "weight": "Bolder",
+                        "size": "Medium",
+                        "color": color,
+                    },
+                    {
+                        "type": "TextBlock",
+                        "text": render_webhook(notification, webhook),
+                        "wrap": true,
+                    },
+                ],
+            },
+        }],
+    })
+}
+
+fn render_webhook(
+    notification: &Notification,
+    webhook: &Webhook,
+) -> String {
+    render(
+        notification,
+        webhook.unavailable_template.as_deref(),
+        webhook.available_template.as_deref(),
+        webhook.degraded_template.as_deref(),
+    )
+}
+
+/// Format seconds as a short human readable duration, e.g. `1h 2m 3s`.
+fn format_duration(seconds: i64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+    match (hours, minutes) {
+        (0, 0) => format!("{}s", seconds),
+        (0, _) => format!("{}m {}s", minutes, seconds),
+        _ => format!("{}h {}m {}s", hours, minutes, seconds),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::notifier::Transition;
+
+    fn notification(event: Transition) -> Notification {
+        Notification {
+            target: "external".to_string(),
+            event,
+            url: Some("https://dns.google/".to_string()),
+            last_error: Some("operation timed out".to_string()),
+            latency_ms: None,
+            outage_seconds: (event == Transition::UnavailableToAvailable).then_some(3725),
+            available_urls: 1,
+            total_urls: 2,
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn webhook() -> Webhook {
+        Webhook::new("https://hooks.example.com".to_string())
+    }
+
+    #[test]
+    fn test_render_default_templates() {
+        assert_eq!(
+            render(
+                &notification(Transition::AvailableToUnavailable),
+                None,
+                None,
+                None
+            ),
+            "external is unavailable, 1/2 urls available, last error from https://dns.google/: \
+             operation timed out"
+        );
+        assert_eq!(
+            render(
+                &notification(Transition::UnavailableToAvailable),
+                None,
+                None,
+                None
+            ),
+            "external is available again after 1h 2m 5s, 1/2 urls available"
+        );
+    }
+
+    #[test]
+    fn test_render_template() {
+        let mut notification = notification(Transition::AvailableToUnavailable);
+        notification.url = None;
+
+        assert_eq!(
+            render(
+                &notification,
+                Some("{status}: {target} ({event}) outage {outage}"),
+                Some("unused"),
+                None
+            ),
+            "unavailable: external (available_to_unavailable) outage -"
+        );
+    }
+
+    #[test]
+    fn test_render_degraded() {
+        let mut notification = notification(Transition::AvailableToDegraded);
+        notification.last_error = None;
+        notification.latency_ms = Some(4212.3);
+
+        assert_eq!(
+            render(&notification, None, None, None),
+            "external is degraded, 1/2 urls available, slowest https://dns.google/ took 4212ms"
+        );
+        assert_eq!(
+            render(
+                &notification,
+                Some("unused"),
+                Some("unused"),
+                Some("{status} ({event})")
+            ),
+            "degraded (available_to_degraded)"
+        );
+        assert_eq!(
+            slack(&notification, &webhook())["text"],
+            format!(
+                ":large_yellow_circle: {}",
+                render(&notification, None, None, None)
+            )
+        );
+        assert_eq!(
+            teams(&notification, &webhook())["attachments"][0]["content"]["body"][0]["color"],
+            "Warning"
+        );
+    }
+
+    #[test]
+    fn test_slack() {
+        assert_eq!(
+            slack(
+                &notification(Transition::UnavailableToAvailable),
+                &webhook()
+            ),
+            json!({
+                "text": ":large_green_circle: external is available again after 1h 2m 5s, \
+                         1/2 urls available"
+            })
+        );
+    }
+
+    #[test]
+    fn test_teams() {
+        let payload = teams(
+            &notification(Transition::AvailableToUnavailable),
+            &webhook(),
+        );
+        let card = &payload["attachments"][0]["content"];
+        assert_eq!(payload["type"], "message");
+        assert_eq!(card["type"], "AdaptiveCard");
+        assert_eq!(card["body"][0]["text"], "external is unavailable");
+        assert_eq!(card["body"][0]["color"], "Attention");
+        assert_eq!(
+            card["body"][1]["text"],
+            render(
+                &notification(Transition::AvailableToUnavailable),
+                None,
+                None,
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0), "0s");
+        assert_eq!(format_duration(59), "59s");
+        assert_eq!(format_duration(61), "1m 1s");
+        assert_eq!(format_duration(3600), "1h 0m 0s");
+    }
+}
@@ -0,0 +1,11 @@
+pub use self::notification::{Notification, Transition};
+pub use self::notifier::{Channel, Error, Notifier};
+pub use self::pagerduty::{PagerDuty, Route, Severity};
+pub use self::webhook::Webhook;
+
+mod format;
+mod notification;
+#[allow(clippy::module_inception)]
+mod notifier;
+mod pagerduty;
+mod webhook;
@@ -0,0 +1,208 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::runner::{Event, State, Target, TargetStatus};
+
+/// Transition is the change of status a Notification is sent for.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Transition {
+    AvailableToUnavailable,
+    UnavailableToAvailable,
+    AvailableToDegraded,
+    DegradedToAvailable,
+    DegradedToUnavailable,
+    UnavailableToDegraded,
+}
+
+impl Transition {
+    /// The name of the transition, as serialized.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Transition::AvailableToUnavailable => "available_to_unavailable",
+            Transition::UnavailableToAvailable => "unavailable_to_available",
+            Transition::AvailableToDegraded => "available_to_degraded",
+            Transition::DegradedToAvailable => "degraded_to_available",
+            Transition::DegradedToUnavailable => "degraded_to_unavailable",
+            Transition::UnavailableToDegraded => "unavailable_to_degraded",
+        }
+    }
+
+    /// The state the target changed to.
+    pub fn state(&self) -> State {
+        match self {
+            Transition::UnavailableToAvailable | Transition::DegradedToAvailable => {
+                State::Available
+            }
+            Transition::AvailableToDegraded | Transition::UnavailableToDegraded => State::Degraded,
+            Transition::AvailableToUnavailable | Transition::DegradedToUnavailable => {
+                State::Unavailable
+            }
+        }
+    }
+}
+
+/// Notification is the payload sent to the notification channels when a target changes status.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Notification {
+    pub target: String,
+    pub event: Transition,
+    /// The url of the target which failed most recently, if any still has an error, else the
+    /// slowest degraded url.
+    pub url: Option<String>,
+    pub last_error: Option<String>,
+    /// The latency of the slowest degraded url, if any.
+    pub latency_ms: Option<f64>,
+    /// How long the target was unavailable or degraded for, set once it leaves that state.
+    pub outage_seconds: Option<i64>,
+    pub available_urls: usize,
+    pub total_urls: usize,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Notification {
+    /// Create a notification for an event of a target, or None if its status did not change.
+    ///
+    /// # Arguments
+    ///
+    /// * `target`: The target being checked.
+    /// * `status`: The status of the target and each of its urls.
+    /// * `event`: The event returned by evaluating the status.
+    ///
+    /// returns: Option<Notification>
+    pub fn new(
+        target: &Target,
+        status: &TargetStatus,
+        event: &Event,
+    ) -> Option<Notification> {
+        let (event, outage_seconds) = match event {
+            Event::AvailableToUnavailable => (Transition::AvailableToUnavailable, None),
+            Event::UnavailableToAvailable(diff) => {
+                (Transition::UnavailableToAvailable, Some(diff.num_seconds()))
+            }
+            Event::AvailableToDegraded => (Transition::AvailableToDegraded, None),
+            Event::DegradedToAvailable(diff) => {
+                (Transition::DegradedToAvailable, Some(diff.num_seconds()))
+            }
+            Event::DegradedToUnavailable => (Transition::DegradedToUnavailable, None),
+            Event::UnavailableToDegraded(diff) => {
+                (Transition::UnavailableToDegraded, Some(diff.num_seconds()))
+            }
+            Event::NoChange => return None,
+        };
+
+        let failed = target
+            .urls
+            .iter()
+            .zip(status.urls.iter())
+            .filter(|(_, status)| status.last_error.is_some())
+            .max_by_key(|(_, status)| status.last_checked);
+        let slowest = target
+            .urls
+            .iter()
+            .zip(status.urls.iter())
+            .filter(|(_, status)| status.state() == State::Degraded)
+            .filter_map(|(url, status)| status.last_latency_ms.map(|latency| (url, latency)))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        Some(Notification {
+            target: target.name.clone(),
+            event,
+            url: failed
+                .map(|(url, _)| url)
+                .or(slowest.map(|(url, _)| url))
+                .map(|url| url.to_string()),
+            last_error: failed.and_then(|(_, status)| status.last_error.clone()),
+            latency_ms: slowest.map(|(_, latency)| latency),
+            outage_seconds,
+            available_urls: status.available(),
+            total_urls: status.urls.len(),
+            timestamp: Utc::now(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+    use pretty_assertions::assert_eq;
+    use reqwest::Url;
+
+    use super::*;
+    use crate::runner::{Quorum, Status};
+
+    fn target() -> Target {
+        Target::new(
+            "mirrors".to_string(),
+            vec![
+                Url::parse("https://one.example.com").unwrap(),
+                Url::parse("https://two.example.com").unwrap(),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_notification_new() {
+        let mut status = TargetStatus::new(Quorum::All, vec![Status::new(1, 1), Status::new(1, 1)]);
+        status.urls[0].last_error = Some("timed out".to_string());
+        status.urls[0].last_checked = Some(Utc::now() - TimeDelta::try_seconds(5).unwrap());
+        status.urls[1].handle_unavailable();
+        status.urls[1].last_error = Some("connection refused".to_string());
+        status.urls[1].last_checked = Some(Utc::now());
+
+        let event = status.evaluate();
+        let notification = Notification::new(&target(), &status, &event).unwrap();
+        assert_eq!(notification.target, "mirrors");
+        assert_eq!(notification.event, Transition::AvailableToUnavailable);
+        assert_eq!(
+            notification.url,
+            Some("https://two.example.com/".to_string())
+        );
+        assert_eq!(
+            notification.last_error,
+            Some("connection refused".to_string())
+        );
+        assert_eq!(notification.outage_seconds, None);
+        assert_eq!(notification.available_urls, 1);
+        assert_eq!(notification.total_urls, 2);
+    }
+
+    #[test]
+    fn test_notification_new_recovered() {
+        let status = TargetStatus::new(Quorum::Any, vec![Status::new(1, 1)]);
+        let event = Event::UnavailableToAvailable(TimeDelta::try_seconds(90).unwrap());
+
+        let notification = Notification::new(&target(), &status, &event).unwrap();
+        assert_eq!(notification.event, Transition::UnavailableToAvailable);
+        assert_eq!(notification.outage_seconds, Some(90));
+        assert_eq!(notification.url, None);
+        assert_eq!(notification.last_error, None);
+
+        assert_eq!(
+            Notification::new(&target(), &status, &Event::NoChange),
+            None
+        );
+    }
+
+    #[test]
+    fn test_notification_new_degraded() {
+        let mut status = TargetStatus::new(Quorum::All, vec![Status::new(1, 1), Status::new(1, 1)]);
+        for (url, latency) in status.urls.iter_mut().zip([4200.0, 3100.0]) {
+            url.handle_slow();
+            url.last_latency_ms = Some(latency);
+        }
+
+        let event = status.evaluate();
+        assert_eq!(event, Event::AvailableToDegraded);
+        let notification = Notification::new(&target(), &status, &event).unwrap();
+        assert_eq!(notification.event, Transition::AvailableToDegraded);
+        assert_eq!(notification.event.state(), State::Degraded);
+        assert_eq!(
+            notification.url,
+            Some("https://one.example.com/".to_string())
+        );
+        assert_eq!(notification.last_error, None);
+        assert_eq!(notification.latency_ms, Some(4200.0));
+        assert_eq!(notification.available_urls, 2);
+    }
+}
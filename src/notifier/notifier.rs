@@ -0,0 +1,299 @@
+use std::sync::{Arc, RwLock};
+
+use futures_util::future::join_all;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use thiserror::Error;
+use tokio_util::task::TaskTracker;
+use tracing::{debug, error};
+
+use crate::notifier::{format, Notification, PagerDuty, Webhook};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("{source}")]
+    ReqwestError {
+        #[from]
+        source: reqwest::Error,
+    },
+    #[error("{source}")]
+    JsonError {
+        #[from]
+        source: serde_json::Error,
+    },
+    #[error("webhook {url} responded with {status}")]
+    StatusError { url: String, status: StatusCode },
+    #[error("invalid webhook header {header}")]
+    InvalidHeader { header: String },
+}
+
+/// Channel is a destination notifications are delivered to.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Channel {
+    /// POST the notification as JSON.
+    Webhook(Webhook),
+    /// POST a message to a Slack incoming webhook.
+    Slack(Webhook),
+    /// POST an Adaptive Card to a Microsoft Teams workflow.
+    Teams(Webhook),
+    /// Trigger and resolve PagerDuty incidents.
+    PagerDuty(PagerDuty),
+}
+
+impl Channel {
+    /// Deliver the notification to the channel.
+    pub async fn send(
+        &self,
+        client: &Client,
+        notification: &Notification,
+    ) -> Result<(), Error> {
+        match self {
+            Channel::Webhook(webhook) => webhook.send(client, notification).await,
+            Channel::Slack(webhook) => {
+                webhook
+                    .send(client, &format::slack(notification, webhook))
+                    .await
+            }
+            Channel::Teams(webhook) => {
+                webhook
+                    .send(client, &format::teams(notification, webhook))
+                    .await
+            }
+            Channel::PagerDuty(pagerduty) => pagerduty.send(client, notification).await,
+        }
+    }
+}
+
+/// Notifier delivers notifications to every configured channel, shared between the runners so
+/// the channels can be replaced when the config is reloaded.
+#[derive(Clone, Debug, Default)]
+pub struct Notifier {
+    channels: Arc<RwLock<Vec<Channel>>>,
+    client: Client,
+    tasks: TaskTracker,
+}
+
+impl Notifier {
+    /// Create a new Notifier with no channels.
+    ///
+    /// returns: Notifier
+    pub fn new() -> Notifier {
+        Notifier::default()
+    }
+
+    /// Replace the channels notifications are delivered to.
+    ///
+    /// # Arguments
+    ///
+    /// * `channels`: The channels to deliver to from now on.
+    pub fn set_channels(
+        &self,
+        channels: Vec<Channel>,
+    ) {
+        *self
+            .channels
+            .write()
+            .expect("Failed to lock the notifier channels") = channels;
+    }
+
+    /// The channels notifications are delivered to.
+    pub fn channels(&self) -> Vec<Channel> {
+        self.channels
+            .read()
+            .expect("Failed to lock the notifier channels")
+            .clone()
+    }
+
+    /// Deliver the notification in the background so the checks are not held up by retries.
+    ///
+    /// # Arguments
+    ///
+    /// * `notification`: The notification to deliver.
+    pub fn notify(
+        &self,
+        notification: Notification,
+    ) {
+        if self.channels().is_empty() {
+            return;
+        }
+
+        let notifier = self.clone();
+        self.tasks.spawn(async move {
+            notifier.send(&notification).await;
+        });
+    }
+
+    /// Wait for every notification sent in the background to be delivered or to run out of
+    /// retries.
+    pub async fn flush(&self) {
+        self.tasks.close();
+        self.tasks.wait().await;
+        self.tasks.reopen();
+    }
+
+    /// Deliver the notification to every channel at once, returning the result of each.
+    ///
+    /// # Arguments
+    ///
+    /// * `notification`: The notification to deliver.
+    ///
+    /// returns: Vec<Result<(), Error>>
+    pub async fn send(
+        &self,
+        notification: &Notification,
+    ) -> Vec<Result<(), Error>> {
+        let channels = self.channels();
+        let results = join_all(
+            channels
+                .iter()
+                .map(|channel| channel.send(&self.client, notification)),
+        )
+        .await;
+
+        for result in &results {
+            match result {
+                Ok(()) => debug!(target_name = notification.target, "notification delivered"),
+                Err(e) => error!(
+                    target_name = notification.target,
+                    "failed to deliver notification: {}", e
+                ),
+            }
+        }
+
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+    use pretty_assertions::assert_eq;
+    use reqwest::Url;
+
+    use super::*;
+    use crate::runner::{Event, Quorum, Status, Target, TargetStatus};
+
+    fn notification() -> Notification {
+        let target = Target::new(
+            "external".to_string(),
+            vec![Url::parse("https://example.com").unwrap()],
+        );
+        let status = TargetStatus::new(Quorum::Any, vec![Status::new(1, 1)]);
+        Notification::new(&target, &status, &Event::AvailableToUnavailable).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_notifier_send() {
+        let server = MockServer::start();
+        let ok = server.mock(|when, then| {
+            when.method(POST).path("/ok");
+            then.status(200);
+        });
+        let failing = server.mock(|when, then| {
+            when.method(POST).path("/failing");
+            then.status(503);
+        });
+
+        let notifier = Notifier::new();
+        notifier.set_channels(vec![
+            Channel::Webhook(Webhook::new(server.url("/ok"))),
+            Channel::Webhook(Webhook {
+                retries: 1,
+                backoff_ms: 1,
+                ..Webhook::new(server.url("/failing"))
+            }),
+        ]);
+
+        let results = notifier.send(&notification()).await;
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        ok.assert();
+        failing.assert_hits(2);
+    }
+
+    #[tokio::test]
+    async fn test_channel_send_slack_teams() {
+        let server = MockServer::start();
+        let slack = server.mock(|when, then| {
+            when.method(POST)
+                .path("/slack")
+                .json_body(serde_json::json!({
+                    "text": ":red_circle: external is down"
+                }));
+            then.status(200);
+        });
+        let teams = server.mock(|when, then| {
+            when.method(POST)
+                .path("/teams")
+                .json_body_partial(r#"{"type": "message"}"#);
+            then.status(202);
+        });
+        let webhook = |path| Webhook {
+            unavailable_template: Some("{target} is down".to_string()),
+            ..Webhook::new(server.url(path))
+        };
+        let client = Client::new();
+
+        Channel::Slack(webhook("/slack"))
+            .send(&client, &notification())
+            .await
+            .unwrap();
+        Channel::Teams(webhook("/teams"))
+            .send(&client, &notification())
+            .await
+            .unwrap();
+        slack.assert();
+        teams.assert();
+    }
+
+    #[tokio::test]
+    async fn test_notifier_flush() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/slow");
+            then.status(200)
+                .delay(std::time::Duration::from_millis(200));
+        });
+
+        let notifier = Notifier::new();
+        notifier.set_channels(vec![Channel::Webhook(Webhook::new(server.url("/slow")))]);
+        notifier.notify(notification());
+        notifier.flush().await;
+        mock.assert();
+    }
+
+    #[test]
+    fn test_channel_deserialize() {
+        let channels: Vec<Channel> = serde_yaml::from_str(
+            r#"
+            - type: webhook
+              url: https://hooks.example.com
+              retries: 5
+            - type: slack
+              url: https://hooks.slack.com/services/T000/B000/XXXX
+              available_template: "{target} is back"
+            - type: pagerduty
+              routing_key: service-key
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            channels,
+            vec![
+                Channel::Webhook(Webhook {
+                    retries: 5,
+                    ..Webhook::new("https://hooks.example.com".to_string())
+                }),
+                Channel::Slack(Webhook {
+                    available_template: Some("{target} is back".to_string()),
+                    ..Webhook::new("https://hooks.slack.com/services/T000/B000/XXXX".to_string())
+                }),
+                Channel::PagerDuty(PagerDuty::new("service-key".to_string())),
+            ]
+        );
+
+        assert!(serde_yaml::from_str::<Vec<Channel>>("- type: email").is_err());
+    }
+}
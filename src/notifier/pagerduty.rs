@@ -0,0 +1,348 @@
+use std::collections::BTreeMap;
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::notifier::webhook::{self, DEFAULT_BACKOFF_MS, DEFAULT_RETRIES, DEFAULT_TIMEOUT_MS};
+use crate::notifier::{format, Error, Notification, Transition, Webhook};
+
+pub const DEFAULT_PAGERDUTY_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+
+/// Severity is the severity of the incidents triggered in PagerDuty.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Critical,
+    Error,
+    Warning,
+    Info,
+}
+
+/// PagerDuty triggers an incident through the Events API v2 when a target becomes unavailable and
+/// resolves it once the target recovers. Degradation is a separate warning incident under its own
+/// dedup key, resolved once the target is no longer degraded.
+///
+/// # Example
+///
+/// ```yaml
+/// notifiers:
+///   - type: pagerduty
+///     routing_key: R0UT1NGK3Y
+///     severity: error
+///     targets:
+///       database:
+///         routing_key: D4T4B4S3K3Y
+///         severity: critical
+/// ```
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PagerDuty {
+    /// The Events API endpoint, only changed to send events to a proxy or mock.
+    #[serde(default = "default_url")]
+    pub url: String,
+    pub routing_key: String,
+    #[serde(default)]
+    pub severity: Severity,
+    /// Routing key and severity overrides keyed by target name.
+    #[serde(default)]
+    pub targets: BTreeMap<String, Route>,
+    #[serde(default = "webhook::default_retries")]
+    pub retries: u32,
+    #[serde(default = "webhook::default_backoff_ms")]
+    pub backoff_ms: u64,
+    #[serde(default = "webhook::default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+/// Route overrides where the events of a single target are sent.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Route {
+    pub routing_key: Option<String>,
+    pub severity: Option<Severity>,
+}
+
+fn default_url() -> String {
+    DEFAULT_PAGERDUTY_URL.to_string()
+}
+
+impl PagerDuty {
+    /// Create a new PagerDuty channel sending critical events with the routing key.
+    ///
+    /// # Arguments
+    ///
+    /// * `routing_key`: The integration key of the PagerDuty service.
+    ///
+    /// returns: PagerDuty
+    pub fn new(routing_key: String) -> PagerDuty {
+        PagerDuty {
+            url: default_url(),
+            routing_key,
+            severity: Severity::default(),
+            targets: BTreeMap::new(),
+            retries: DEFAULT_RETRIES,
+            backoff_ms: DEFAULT_BACKOFF_MS,
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+        }
+    }
+
+    /// Deliver the notification as trigger and resolve events.
+    ///
+    /// # Arguments
+    ///
+    /// * `client`: The client used to send the request.
+    /// * `notification`: The notification to deliver.
+    ///
+    /// returns: Result<(), Error>
+    pub async fn send(
+        &self,
+        client: &Client,
+        notification: &Notification,
+    ) -> Result<(), Error> {
+        let webhook = Webhook {
+            retries: self.retries,
+            backoff_ms: self.backoff_ms,
+            timeout_ms: self.timeout_ms,
+            ..Webhook::new(self.url.clone())
+        };
+
+        for event in self.events(notification) {
+            webhook.send(client, &event).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Build the Events API v2 payloads for the notification, in the order they are sent.
+    pub fn events(
+        &self,
+        notification: &Notification,
+    ) -> Vec<Value> {
+        let route = self
+            .targets
+            .get(&notification.target)
+            .cloned()
+            .unwrap_or_default();
+        let routing_key = route
+            .routing_key
+            .unwrap_or_else(|| self.routing_key.clone());
+        let outage_key = dedup_key(&notification.target);
+        let degraded_key = format!("{}-degraded", outage_key);
+
+        let trigger = |dedup_key: &str, severity: Severity| {
+            json!({
+                "routing_key": routing_key,
+                "event_action": "trigger",
+                "dedup_key": dedup_key,
+                "payload": {
+                    "summary": format::render(notification, None, None, None),
+                    "source": notification.url.as_deref().unwrap_or(&notification.target),
+                    "severity": severity,
+                    "timestamp": notification.timestamp.to_rfc3339(),
+                    "component": notification.target,
+                    "custom_details": notification,
+                },
+            })
+        };
+        let resolve = |dedup_key: &str| {
+            json!({
+                "routing_key": routing_key,
+                "event_action": "resolve",
+                "dedup_key": dedup_key,
+            })
+        };
+        let severity = route.severity.unwrap_or(self.severity);
+
+        // The outage and the degradation are separate incidents, so leaving the degraded state
+        // always resolves the warning whichever state the target moves to.
+        match notification.event {
+            Transition::AvailableToUnavailable => vec![trigger(&outage_key, severity)],
+            Transition::UnavailableToAvailable => vec![resolve(&outage_key)],
+            Transition::AvailableToDegraded => vec![trigger(&degraded_key, Severity::Warning)],
+            Transition::DegradedToAvailable => vec![resolve(&degraded_key)],
+            Transition::DegradedToUnavailable => {
+                vec![resolve(&degraded_key), trigger(&outage_key, severity)]
+            }
+            Transition::UnavailableToDegraded => {
+                vec![
+                    resolve(&outage_key),
+                    trigger(&degraded_key, Severity::Warning),
+                ]
+            }
+        }
+    }
+}
+
+/// The dedup key shared by the outage trigger and resolve events of a target.
+fn dedup_key(target: &str) -> String {
+    format!("netcheck-{}", target)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use httpmock::prelude::*;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn notification(event: Transition) -> Notification {
+        Notification {
+            target: "database".to_string(),
+            event,
+            url: Some("tcp://postgres:5432".to_string()),
+            last_error: Some("connection refused".to_string()),
+            latency_ms: None,
+            outage_seconds: None,
+            available_urls: 0,
+            total_urls: 1,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_pagerduty_event_trigger() {
+        let pagerduty = PagerDuty::new("service-key".to_string());
+        let events = pagerduty.events(&notification(Transition::AvailableToUnavailable));
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+
+        assert_eq!(event["routing_key"], "service-key");
+        assert_eq!(event["event_action"], "trigger");
+        assert_eq!(event["dedup_key"], "netcheck-database");
+        assert_eq!(event["payload"]["severity"], "critical");
+        assert_eq!(event["payload"]["source"], "tcp://postgres:5432");
+        assert_eq!(event["payload"]["component"], "database");
+        assert_eq!(
+            event["payload"]["custom_details"]["last_error"],
+            "connection refused"
+        );
+    }
+
+    #[test]
+    fn test_pagerduty_event_resolve_route() {
+        let mut pagerduty = PagerDuty::new("service-key".to_string());
+        pagerduty.targets.insert(
+            "database".to_string(),
+            Route {
+                routing_key: Some("database-key".to_string()),
+                severity: Some(Severity::Warning),
+            },
+        );
+
+        let trigger = pagerduty.events(&notification(Transition::AvailableToUnavailable));
+        assert_eq!(trigger[0]["routing_key"], "database-key");
+        assert_eq!(trigger[0]["payload"]["severity"], "warning");
+
+        let resolve = pagerduty.events(&notification(Transition::UnavailableToAvailable));
+        assert_eq!(
+            resolve,
+            vec![json!({
+                "routing_key": "database-key",
+                "event_action": "resolve",
+                "dedup_key": "netcheck-database",
+            })]
+        );
+    }
+
+    #[test]
+    fn test_pagerduty_events_degraded() {
+        let pagerduty = PagerDuty::new("service-key".to_string());
+        let keys = |transition: Transition| {
+            pagerduty
+                .events(&notification(transition))
+                .iter()
+                .map(|event| {
+                    format!(
+                        "{} {} {}",
+                        event["event_action"].as_str().unwrap(),
+                        event["dedup_key"].as_str().unwrap(),
+                        event["payload"]["severity"].as_str().unwrap_or("-")
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            keys(Transition::AvailableToDegraded),
+            vec!["trigger netcheck-database-degraded warning"]
+        );
+        assert_eq!(
+            keys(Transition::DegradedToUnavailable),
+            vec![
+                "resolve netcheck-database-degraded -",
+                "trigger netcheck-database critical"
+            ]
+        );
+        assert_eq!(
+            keys(Transition::UnavailableToDegraded),
+            vec![
+                "resolve netcheck-database -",
+                "trigger netcheck-database-degraded warning"
+            ]
+        );
+        assert_eq!(
+            keys(Transition::DegradedToAvailable),
+            vec!["resolve netcheck-database-degraded -"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pagerduty_send() {
+        let server = MockServer::start();
+        let trigger = server.mock(|when, then| {
+            when.method(POST).path("/v2/enqueue").json_body_partial(
+                r#"{"event_action": "trigger", "dedup_key": "netcheck-database"}"#,
+            );
+            then.status(202)
+                .body(r#"{"status": "success", "dedup_key": "netcheck-database"}"#);
+        });
+        let resolve = server.mock(|when, then| {
+            when.method(POST).path("/v2/enqueue").json_body_partial(
+                r#"{"event_action": "resolve", "dedup_key": "netcheck-database"}"#,
+            );
+            then.status(202);
+        });
+
+        let pagerduty = PagerDuty {
+            url: server.url("/v2/enqueue"),
+            ..PagerDuty::new("service-key".to_string())
+        };
+        let client = Client::new();
+        pagerduty
+            .send(&client, &notification(Transition::AvailableToUnavailable))
+            .await
+            .unwrap();
+        pagerduty
+            .send(&client, &notification(Transition::UnavailableToAvailable))
+            .await
+            .unwrap();
+        trigger.assert();
+        resolve.assert();
+    }
+
+    #[test]
+    fn test_pagerduty_deserialize() {
+        let pagerduty: PagerDuty = serde_yaml::from_str(
+            r#"
+            routing_key: service-key
+            severity: error
+            targets:
+              database:
+                severity: critical
+            "#,
+        )
+        .unwrap();
+        assert_eq!(pagerduty.url, DEFAULT_PAGERDUTY_URL);
+        assert_eq!(pagerduty.severity, Severity::Error);
+        assert_eq!(
+            pagerduty.targets["database"],
+            Route {
+                routing_key: None,
+                severity: Some(Severity::Critical),
+            }
+        );
+    }
+}
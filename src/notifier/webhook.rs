@@ -0,0 +1,259 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tokio::time;
+use tracing::warn;
+
+use crate::notifier::Error;
+
+pub const DEFAULT_RETRIES: u32 = 3;
+pub const DEFAULT_BACKOFF_MS: u64 = 500;
+pub const DEFAULT_TIMEOUT_MS: u64 = 5000;
+
+/// Webhook POSTs each notification as JSON to an endpoint, retrying failed deliveries.
+///
+/// # Example
+///
+/// ```yaml
+/// notifiers:
+///   - type: webhook
+///     url: https://hooks.example.com/netcheck
+///     headers:
+///       Authorization: Bearer token
+///     retries: 3
+///     backoff_ms: 500
+///   - type: slack
+///     url: https://hooks.slack.com/services/T000/B000/XXXX
+///     unavailable_template: "{target} is down: {last_error}"
+/// ```
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Attempts made after the first delivery fails.
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// Delay before the first retry, doubled for each following retry.
+    #[serde(default = "default_backoff_ms")]
+    pub backoff_ms: u64,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Text sent by `slack` and `teams` channels when the target becomes unavailable.
+    pub unavailable_template: Option<String>,
+    /// Text sent by `slack` and `teams` channels when the target becomes available.
+    pub available_template: Option<String>,
+    /// Text sent by `slack` and `teams` channels when the target becomes degraded.
+    pub degraded_template: Option<String>,
+}
+
+pub(crate) fn default_retries() -> u32 {
+    DEFAULT_RETRIES
+}
+
+pub(crate) fn default_backoff_ms() -> u64 {
+    DEFAULT_BACKOFF_MS
+}
+
+pub(crate) fn default_timeout_ms() -> u64 {
+    DEFAULT_TIMEOUT_MS
+}
+
+impl Webhook {
+    /// Create a new Webhook to the url with the default retries and backoff.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The endpoint the notifications are POSTed to.
+    ///
+    /// returns: Webhook
+    pub fn new(url: String) -> Webhook {
+        Webhook {
+            url,
+            headers: BTreeMap::new(),
+            retries: DEFAULT_RETRIES,
+            backoff_ms: DEFAULT_BACKOFF_MS,
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+            unavailable_template: None,
+            available_template: None,
+            degraded_template: None,
+        }
+    }
+
+    /// Deliver the payload as JSON, retrying with an exponential backoff until it is accepted or
+    /// the retries run out.
+    ///
+    /// # Arguments
+    ///
+    /// * `client`: The client used to send the request.
+    /// * `payload`: The notification, or a payload formatted from it.
+    ///
+    /// returns: Result<(), Error>
+    pub async fn send<T: Serialize>(
+        &self,
+        client: &Client,
+        payload: &T,
+    ) -> Result<(), Error> {
+        let body = serde_json::to_vec(payload)?;
+        let headers = self.header_map()?;
+
+        let mut attempt = 0;
+        loop {
+            let result = self.post(client, headers.clone(), body.clone()).await;
+            match result {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt >= self.retries => return Err(e),
+                Err(e) => {
+                    let backoff = self.backoff_ms.saturating_mul(2u64.saturating_pow(attempt));
+                    warn!(
+                        webhook = self.url,
+                        attempt = attempt + 1,
+                        backoff_ms = backoff,
+                        "failed to deliver notification, retrying: {}",
+                        e
+                    );
+                    time::sleep(Duration::from_millis(backoff)).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    async fn post(
+        &self,
+        client: &Client,
+        headers: HeaderMap,
+        body: Vec<u8>,
+    ) -> Result<(), Error> {
+        let resp = client
+            .post(&self.url)
+            .headers(headers)
+            .timeout(Duration::from_millis(self.timeout_ms))
+            .body(body)
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            return Err(Error::StatusError {
+                url: self.url.clone(),
+                status: resp.status(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn header_map(&self) -> Result<HeaderMap, Error> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        for (name, value) in &self.headers {
+            let invalid = || Error::InvalidHeader {
+                header: name.clone(),
+            };
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?,
+                HeaderValue::from_str(value).map_err(|_| invalid())?,
+            );
+        }
+
+        Ok(headers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+    use pretty_assertions::assert_eq;
+    use reqwest::Url;
+
+    use super::*;
+    use crate::notifier::Notification;
+    use crate::runner::{Event, Quorum, Status, Target, TargetStatus};
+
+    fn notification() -> Notification {
+        let target = Target::new(
+            "external".to_string(),
+            vec![Url::parse("https://example.com").unwrap()],
+        );
+        let status = TargetStatus::new(Quorum::Any, vec![Status::new(1, 1)]);
+        Notification::new(&target, &status, &Event::AvailableToUnavailable).unwrap()
+    }
+
+    fn webhook(url: String) -> Webhook {
+        Webhook {
+            retries: 2,
+            backoff_ms: 1,
+            ..Webhook::new(url)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_webhook_send() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/hook")
+                .header("content-type", "application/json")
+                .header("authorization", "Bearer token")
+                .json_body_partial(
+                    r#"{"target": "external", "event": "available_to_unavailable"}"#,
+                );
+            then.status(204);
+        });
+
+        let mut webhook = webhook(server.url("/hook"));
+        webhook
+            .headers
+            .insert("Authorization".to_string(), "Bearer token".to_string());
+        webhook.send(&Client::new(), &notification()).await.unwrap();
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_webhook_send_retries() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/hook");
+            then.status(500);
+        });
+
+        let err = webhook(server.url("/hook"))
+            .send(&Client::new(), &notification())
+            .await
+            .unwrap_err();
+        mock.assert_hits(3);
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "webhook {} responded with 500 Internal Server Error",
+                server.url("/hook")
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_webhook_send_invalid_header() {
+        let mut webhook = webhook("http://127.0.0.1:1".to_string());
+        webhook
+            .headers
+            .insert("Bad Header".to_string(), "value".to_string());
+        let err = webhook
+            .send(&Client::new(), &notification())
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "invalid webhook header Bad Header");
+    }
+
+    #[test]
+    fn test_webhook_deserialize_defaults() {
+        let webhook: Webhook = serde_yaml::from_str("url: https://hooks.example.com").unwrap();
+        assert_eq!(
+            webhook,
+            Webhook::new("https://hooks.example.com".to_string())
+        );
+    }
+}
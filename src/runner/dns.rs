@@ -0,0 +1,380 @@
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use hickory_resolver::config::{
+    NameServerConfig, NameServerConfigGroup, Protocol, ResolverConfig, ResolverOpts,
+};
+use hickory_resolver::proto::rr::{RData, RecordType};
+use hickory_resolver::TokioAsyncResolver;
+use reqwest::Url;
+
+use crate::runner::Error;
+
+pub const SCHEME_DNS: &str = "dns";
+
+const DEFAULT_DNS_PORT: u16 = 53;
+const QUERY_TYPE: &str = "type";
+const QUERY_EXPECT: &str = "expect";
+const QUERY_PROTOCOL: &str = "protocol";
+
+/// Query is a DNS lookup described by a `dns://` url.
+///
+/// The url takes the form `dns://resolver[:port]/name?type=A&expect=1.2.3.4&protocol=udp`, where
+/// the resolver is optional and falls back to the system configuration when omitted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Query {
+    pub resolver: Option<SocketAddr>,
+    pub protocol: Protocol,
+    pub name: String,
+    pub record_type: RecordType,
+    pub expect: Vec<String>,
+}
+
+impl Query {
+    /// Create a Query from a `dns://` url.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The url describing the lookup.
+    ///
+    /// returns: Result<Query, Error>
+    pub fn from_url(url: &Url) -> Result<Query, Error> {
+        let invalid = |reason: &str| Error::InvalidUrl {
+            url: url.to_string(),
+            reason: reason.to_string(),
+        };
+
+        let resolver = match url.host_str().filter(|host| !host.is_empty()) {
+            Some(host) => {
+                let ip = host
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .parse::<IpAddr>()
+                    .map_err(|_| invalid("resolver must be an ip address"))?;
+                Some(SocketAddr::new(ip, url.port().unwrap_or(DEFAULT_DNS_PORT)))
+            }
+            None => None,
+        };
+
+        let name = url.path().trim_start_matches('/').to_string();
+        if name.is_empty() {
+            return Err(invalid("missing name to resolve"));
+        }
+
+        let mut query = Query {
+            resolver,
+            protocol: Protocol::Udp,
+            name,
+            record_type: RecordType::A,
+            expect: vec![],
+        };
+
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                QUERY_TYPE => {
+                    query.record_type = match value.to_uppercase().as_str() {
+                        "A" => RecordType::A,
+                        "AAAA" => RecordType::AAAA,
+                        "CNAME" => RecordType::CNAME,
+                        "TXT" => RecordType::TXT,
+                        _ => return Err(invalid("unsupported record type")),
+                    }
+                }
+                QUERY_PROTOCOL => {
+                    query.protocol = match value.to_lowercase().as_str() {
+                        "udp" => Protocol::Udp,
+                        "tcp" => Protocol::Tcp,
+                        _ => return Err(invalid("unsupported protocol")),
+                    }
+                }
+                QUERY_EXPECT => query.expect.push(normalize(&value)),
+                _ => {}
+            }
+        }
+
+        Ok(query)
+    }
+
+    /// Resolve the query, checking the answers contain every expected value.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout_ms`: Timeout in milliseconds to be considered a failure.
+    ///
+    /// returns: Result<(), Error>
+    pub async fn resolve(
+        &self,
+        timeout_ms: u64,
+    ) -> Result<(), Error> {
+        let resolver = self.resolver(timeout_ms)?;
+        let lookup = resolver
+            .lookup(self.name.as_str(), self.record_type)
+            .await?;
+
+        let answers: Vec<String> = lookup.iter().filter_map(answer_to_string).collect();
+        let found: HashSet<&String> = answers.iter().collect();
+        if self.expect.iter().any(|expected| !found.contains(expected)) {
+            return Err(Error::DnsAnswerMismatch {
+                expected: self.expect.clone(),
+                actual: answers,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn resolver(
+        &self,
+        timeout_ms: u64,
+    ) -> Result<TokioAsyncResolver, Error> {
+        let config = match self.resolver {
+            Some(addr) => ResolverConfig::from_parts(
+                None,
+                vec![],
+                NameServerConfigGroup::from(vec![NameServerConfig::new(addr, self.protocol)]),
+            ),
+            None => hickory_resolver::system_conf::read_system_conf()?.0,
+        };
+
+        let mut opts = ResolverOpts::default();
+        opts.timeout = Duration::from_millis(timeout_ms);
+        opts.attempts = 1;
+        opts.cache_size = 0;
+        opts.use_hosts_file = false;
+
+        Ok(TokioAsyncResolver::tokio(config, opts))
+    }
+}
+
+/// Resolve the `dns://` url.
+///
+/// # Arguments
+///
+/// * `url`: The url describing the lookup.
+/// * `timeout_ms`: Timeout in milliseconds to be considered a failure.
+///
+/// returns: Result<(), Error>
+pub async fn resolve(
+    url: &Url,
+    timeout_ms: u64,
+) -> Result<(), Error> {
+    Query::from_url(url)?.resolve(timeout_ms).await
+}
+
+fn answer_to_string(rdata: &RData) -> Option<String> {
+    match rdata {
+        RData::A(a) => Some(a.to_string()),
+        RData::AAAA(aaaa) => Some(aaaa.to_string()),
+        RData::CNAME(cname) => Some(normalize(&cname.to_string())),
+        RData::TXT(txt) => Some(
+            txt.txt_data()
+                .iter()
+                .map(|data| String::from_utf8_lossy(data).to_string())
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Names are compared without their trailing root dot so `example.com` matches `example.com.`.
+fn normalize(value: &str) -> String {
+    value.trim_end_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+    use std::str::FromStr;
+
+    use hickory_resolver::proto::op::{Message, MessageType};
+    use hickory_resolver::proto::rr::rdata::{A, TXT};
+    use hickory_resolver::proto::rr::{Name, Record};
+    use pretty_assertions::assert_eq;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, UdpSocket};
+
+    use super::*;
+
+    fn answer(
+        request: &[u8],
+        answers: &[RData],
+    ) -> Vec<u8> {
+        let request = Message::from_vec(request).expect("failed to parse dns request");
+        let mut response = Message::new();
+        response
+            .set_id(request.id())
+            .set_message_type(MessageType::Response)
+            .set_recursion_desired(true)
+            .set_recursion_available(true)
+            .add_queries(request.queries().to_vec());
+
+        let name = request.queries()[0].name().clone();
+        for rdata in answers {
+            response.add_answer(Record::from_rdata(name.clone(), 60, rdata.clone()));
+        }
+
+        response.to_vec().expect("failed to encode dns response")
+    }
+
+    /// Start a stub DNS server over UDP which answers every query with the given records.
+    async fn udp_stub(answers: Vec<RData>) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+                let response = answer(&buf[..len], &answers);
+                let _ = socket.send_to(&response, peer).await;
+            }
+        });
+
+        addr
+    }
+
+    /// Start a stub DNS server over TCP which answers every query with the given records.
+    async fn tcp_stub(answers: Vec<RData>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut len = [0u8; 2];
+                if stream.read_exact(&mut len).await.is_err() {
+                    continue;
+                }
+                let mut buf = vec![0u8; u16::from_be_bytes(len) as usize];
+                if stream.read_exact(&mut buf).await.is_err() {
+                    continue;
+                }
+                let response = answer(&buf, &answers);
+                let _ = stream
+                    .write_all(&(response.len() as u16).to_be_bytes())
+                    .await;
+                let _ = stream.write_all(&response).await;
+            }
+        });
+
+        addr
+    }
+
+    #[test]
+    fn test_query_from_url() {
+        let url = Url::parse(
+            "dns://127.0.0.1:5353/example.com?type=aaaa&expect=::1&expect=::2&protocol=tcp",
+        )
+        .unwrap();
+        let query = Query::from_url(&url).unwrap();
+
+        assert_eq!(
+            query,
+            Query {
+                resolver: Some(SocketAddr::from_str("127.0.0.1:5353").unwrap()),
+                protocol: Protocol::Tcp,
+                name: "example.com".to_string(),
+                record_type: RecordType::AAAA,
+                expect: vec!["::1".to_string(), "::2".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_query_from_url_defaults() {
+        let url = Url::parse("dns://[::1]/example.com").unwrap();
+        let query = Query::from_url(&url).unwrap();
+
+        assert_eq!(
+            query.resolver,
+            Some(SocketAddr::from_str("[::1]:53").unwrap())
+        );
+        assert_eq!(query.protocol, Protocol::Udp);
+        assert_eq!(query.record_type, RecordType::A);
+        assert_eq!(query.expect, Vec::<String>::new());
+
+        let url = Url::parse("dns:///example.com").unwrap();
+        assert_eq!(Query::from_url(&url).unwrap().resolver, None);
+    }
+
+    #[test]
+    fn test_query_from_url_invalid() {
+        for (url, expected) in [
+            (
+                "dns://resolver.local/example.com",
+                "resolver must be an ip address",
+            ),
+            ("dns://127.0.0.1", "missing name to resolve"),
+            (
+                "dns://127.0.0.1/example.com?type=MX",
+                "unsupported record type",
+            ),
+            (
+                "dns://127.0.0.1/example.com?protocol=quic",
+                "unsupported protocol",
+            ),
+        ] {
+            match Query::from_url(&Url::parse(url).unwrap()) {
+                Err(Error::InvalidUrl { reason, .. }) => assert_eq!(reason, expected),
+                other => panic!("Expected InvalidUrl for {}, got {:?}", url, other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_udp() {
+        let addr = udp_stub(vec![RData::A(A(Ipv4Addr::new(10, 0, 0, 1)))]).await;
+        let url = Url::parse(&format!("dns://{}/example.com?expect=10.0.0.1", addr)).unwrap();
+
+        resolve(&url, 1000).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resolve_tcp() {
+        let addr = tcp_stub(vec![RData::TXT(TXT::new(vec!["v=spf1 -all".to_string()]))]).await;
+        let url = Url::parse(&format!(
+            "dns://{}/example.com?type=TXT&protocol=tcp&expect=v%3Dspf1%20-all",
+            addr
+        ))
+        .unwrap();
+
+        resolve(&url, 1000).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resolve_cname() {
+        let addr = udp_stub(vec![RData::CNAME(
+            hickory_resolver::proto::rr::rdata::CNAME(Name::from_str("edge.example.net.").unwrap()),
+        )])
+        .await;
+        let url = Url::parse(&format!(
+            "dns://{}/www.example.com?type=CNAME&expect=edge.example.net",
+            addr
+        ))
+        .unwrap();
+
+        resolve(&url, 1000).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resolve_answer_mismatch() {
+        let addr = udp_stub(vec![RData::A(A(Ipv4Addr::new(10, 0, 0, 2)))]).await;
+        let url = Url::parse(&format!("dns://{}/example.com?expect=10.0.0.1", addr)).unwrap();
+
+        match resolve(&url, 1000).await {
+            Err(Error::DnsAnswerMismatch { expected, actual }) => {
+                assert_eq!(expected, vec!["10.0.0.1".to_string()]);
+                assert_eq!(actual, vec!["10.0.0.2".to_string()]);
+            }
+            other => panic!("Expected DnsAnswerMismatch, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_no_answer() {
+        let addr = udp_stub(vec![]).await;
+        let url = Url::parse(&format!("dns://{}/example.com", addr)).unwrap();
+
+        match resolve(&url, 1000).await {
+            Err(Error::DnsError { .. }) => {}
+            other => panic!("Expected DnsError, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+
+use crate::runner::{Event, ProbeResult};
+
+/// The number of events buffered for each subscriber before the oldest are dropped.
+pub const DEFAULT_EVENTS_CAPACITY: usize = 128;
+
+/// RunnerEvent is published by a Runner for each check of a url and each change of the target
+/// status, see `Runner::subscribe`.
+// Most events are UrlChecked, so boxing its result would only add an allocation per check.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum RunnerEvent {
+    /// A url of the target was checked.
+    UrlChecked {
+        target: String,
+        url: Url,
+        result: ProbeResult,
+        /// The change of the url status caused by the check.
+        event: Event,
+        is_unavailable: bool,
+        timestamp: DateTime<Utc>,
+    },
+    /// The status of the target changed.
+    TargetChanged {
+        target: String,
+        event: Event,
+        available_urls: usize,
+        total_urls: usize,
+        timestamp: DateTime<Utc>,
+    },
+}
+
+impl RunnerEvent {
+    /// The name of the target the event is for.
+    pub fn target(&self) -> &str {
+        match self {
+            RunnerEvent::UrlChecked { target, .. } => target,
+            RunnerEvent::TargetChanged { target, .. } => target,
+        }
+    }
+}
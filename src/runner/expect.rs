@@ -0,0 +1,325 @@
+use std::fmt;
+use std::str::FromStr;
+
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::runner::url::vec_to_string;
+use crate::runner::Error;
+
+/// StatusRange is an inclusive range of response status codes, written as `200`, `200-299` or
+/// `2xx`.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(try_from = "StatusRangeValue")]
+pub struct StatusRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StatusRangeValue {
+    Code(u16),
+    Range(String),
+}
+
+/// BodyAssertion is a check of the response body.
+#[derive(Clone, Debug)]
+pub enum BodyAssertion {
+    /// The body contains the text.
+    Contains(String),
+    /// The body matches the regex.
+    Regex(Regex),
+    /// The body is JSON and the value at the pointer equals the value.
+    JsonPointer { pointer: String, equals: Value },
+}
+
+/// Expectations are what a response has to match for a check to succeed.
+///
+/// Without status ranges any status below 400 is expected, every body assertion has to pass.
+#[derive(Clone, Debug, Default)]
+pub struct Expectations {
+    pub status: Vec<StatusRange>,
+    pub body: Vec<BodyAssertion>,
+}
+
+impl StatusRange {
+    /// Whether the status is within the range.
+    pub fn contains(
+        &self,
+        status: u16,
+    ) -> bool {
+        (self.start..=self.end).contains(&status)
+    }
+}
+
+impl FromStr for StatusRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid status {}, expected a code, a range like 200-299 or a class like 2xx",
+                s
+            )
+        };
+        let code = |code: &str| {
+            code.trim()
+                .parse::<u16>()
+                .ok()
+                .filter(|code| (100..=599).contains(code))
+                .ok_or_else(invalid)
+        };
+
+        let range = match s.split_once('-') {
+            Some((start, end)) => StatusRange {
+                start: code(start)?,
+                end: code(end)?,
+            },
+            None if s.len() == 3 && s.get(1..).is_some_and(|x| x.eq_ignore_ascii_case("xx")) => {
+                let class = code(&format!("{}00", &s[..1]))?;
+                StatusRange {
+                    start: class,
+                    end: class + 99,
+                }
+            }
+            None => {
+                let code = code(s)?;
+                StatusRange {
+                    start: code,
+                    end: code,
+                }
+            }
+        };
+        if range.start > range.end {
+            return Err(invalid());
+        }
+
+        Ok(range)
+    }
+}
+
+impl TryFrom<StatusRangeValue> for StatusRange {
+    type Error = String;
+
+    fn try_from(value: StatusRangeValue) -> Result<Self, Self::Error> {
+        match value {
+            StatusRangeValue::Code(code) => code.to_string().parse(),
+            StatusRangeValue::Range(range) => range.parse(),
+        }
+    }
+}
+
+impl fmt::Display for StatusRange {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        if self.start == self.end {
+            return write!(f, "{}", self.start);
+        }
+
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+impl BodyAssertion {
+    /// Check the body, returning which assertion failed and why.
+    ///
+    /// # Arguments
+    ///
+    /// * `body`: The response body.
+    ///
+    /// returns: Result<(), Error>
+    pub fn check(
+        &self,
+        body: &[u8],
+    ) -> Result<(), Error> {
+        let failed = |reason: String| Error::AssertionFailed {
+            assertion: self.to_string(),
+            reason,
+        };
+
+        match self {
+            BodyAssertion::Contains(text) => {
+                if !String::from_utf8_lossy(body).contains(text.as_str()) {
+                    return Err(failed("text not found in body".to_string()));
+                }
+            }
+            BodyAssertion::Regex(regex) => {
+                if !regex.is_match(&String::from_utf8_lossy(body)) {
+                    return Err(failed("no match in body".to_string()));
+                }
+            }
+            BodyAssertion::JsonPointer { pointer, equals } => {
+                let json = serde_json::from_slice::<Value>(body)
+                    .map_err(|err| failed(format!("body is not JSON: {}", err)))?;
+                match json.pointer(pointer) {
+                    Some(value) if value == equals => {}
+                    Some(value) => return Err(failed(format!("found {}", value))),
+                    None => return Err(failed("pointer not found".to_string())),
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for BodyAssertion {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self {
+            BodyAssertion::Contains(text) => write!(f, "contains {:?}", text),
+            BodyAssertion::Regex(regex) => write!(f, "regex {:?}", regex.as_str()),
+            BodyAssertion::JsonPointer { pointer, equals } => {
+                write!(f, "json_pointer {} equals {}", pointer, equals)
+            }
+        }
+    }
+}
+
+impl Expectations {
+    /// Check the response, returning the first expectation it does not meet.
+    ///
+    /// # Arguments
+    ///
+    /// * `status`: The response status code.
+    /// * `body`: The response body.
+    ///
+    /// returns: Result<(), Error>
+    pub fn check(
+        &self,
+        status: u16,
+        body: &[u8],
+    ) -> Result<(), Error> {
+        if self.status.is_empty() {
+            if status >= 400 {
+                return Err(Error::StatusError { status });
+            }
+        } else if !self.status.iter().any(|range| range.contains(status)) {
+            return Err(Error::UnexpectedStatus {
+                status,
+                expected: vec_to_string(self.status.clone()),
+            });
+        }
+
+        self.body
+            .iter()
+            .try_for_each(|assertion| assertion.check(body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_status_range_from_str() {
+        assert_eq!(
+            "404".parse(),
+            Ok(StatusRange {
+                start: 404,
+                end: 404
+            })
+        );
+        assert_eq!(
+            "200-204".parse(),
+            Ok(StatusRange {
+                start: 200,
+                end: 204
+            })
+        );
+        assert_eq!(
+            "4xx".parse(),
+            Ok(StatusRange {
+                start: 400,
+                end: 499
+            })
+        );
+        assert!("299-200".parse::<StatusRange>().is_err());
+        assert!("9xx".parse::<StatusRange>().is_err());
+        assert!("€".parse::<StatusRange>().is_err());
+        assert!("éx".parse::<StatusRange>().is_err());
+        assert_eq!(
+            "ok".parse::<StatusRange>(),
+            Err(
+                "invalid status ok, expected a code, a range like 200-299 or a class like 2xx"
+                    .to_string()
+            )
+        );
+
+        let ranges: Vec<StatusRange> = serde_yaml::from_str("[401, 2xx, \"300-302\"]").unwrap();
+        assert_eq!(vec_to_string(ranges), "401,200-299,300-302");
+    }
+
+    #[test]
+    fn test_expectations_status() {
+        let default = Expectations::default();
+        assert!(default.check(302, b"").is_ok());
+        assert_eq!(
+            default.check(404, b"").unwrap_err().to_string(),
+            "404".to_string()
+        );
+
+        let expectations = Expectations {
+            status: vec!["401".parse().unwrap(), "2xx".parse().unwrap()],
+            body: vec![],
+        };
+        assert!(expectations.check(401, b"").is_ok());
+        assert_eq!(
+            expectations.check(302, b"").unwrap_err().to_string(),
+            "status 302 not in 401,200-299"
+        );
+    }
+
+    #[test]
+    fn test_body_assertions() {
+        let body = br#"{"status": "UP", "version": "2.1.0"}"#;
+        let assertion = |assertion: BodyAssertion| assertion.check(body).map_err(|e| e.to_string());
+
+        assert_eq!(
+            assertion(BodyAssertion::Contains("\"UP\"".to_string())),
+            Ok(())
+        );
+        assert_eq!(
+            assertion(BodyAssertion::Contains("DOWN".to_string())),
+            Err("assertion contains \"DOWN\" failed: text not found in body".to_string())
+        );
+        assert_eq!(
+            assertion(BodyAssertion::Regex(
+                Regex::new(r#""version": "2\.\d+"#).unwrap()
+            )),
+            Ok(())
+        );
+        assert_eq!(
+            assertion(BodyAssertion::JsonPointer {
+                pointer: "/status".to_string(),
+                equals: json!("UP"),
+            }),
+            Ok(())
+        );
+        assert_eq!(
+            assertion(BodyAssertion::JsonPointer {
+                pointer: "/version".to_string(),
+                equals: json!("3.0.0"),
+            }),
+            Err(
+                "assertion json_pointer /version equals \"3.0.0\" failed: found \"2.1.0\""
+                    .to_string()
+            )
+        );
+        assert!(BodyAssertion::JsonPointer {
+            pointer: "/status".to_string(),
+            equals: json!("UP"),
+        }
+        .check(b"UP")
+        .is_err());
+    }
+}
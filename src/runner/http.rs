@@ -0,0 +1,978 @@
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use hyper::body::{Bytes, HttpBody};
+use hyper::client::connect::{Connected, Connection};
+use hyper::service::Service;
+use hyper::{Body, Request, Uri};
+use reqwest::header::{
+    HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, COOKIE,
+    LOCATION, PROXY_AUTHORIZATION, TRANSFER_ENCODING, WWW_AUTHENTICATE,
+};
+use reqwest::{Method, StatusCode, Url};
+use serde::Deserialize;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{lookup_host, TcpStream};
+use tokio::time;
+use tokio_native_tls::TlsStream;
+
+use crate::runner::proxy::Proxies;
+use crate::runner::Error;
+
+pub const SCHEME_HTTPS: &str = "https";
+
+/// How much of the response body is read, the rest is discarded.
+pub const MAX_BODY_BYTES: usize = 1024 * 1024;
+/// How many redirects are followed, like reqwest and browsers.
+pub const MAX_REDIRECTS: usize = 10;
+
+const DEFAULT_HTTP_PORT: u16 = 80;
+const DEFAULT_HTTPS_PORT: u16 = 443;
+
+const PEM_CERTIFICATE_END: &str = "-----END CERTIFICATE-----";
+
+/// Timings holds how long each phase of a request took, phases which were not reached are left
+/// empty.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Timings {
+    /// Resolving the host.
+    pub dns: Option<Duration>,
+    /// Opening the TCP connection, and the tunnel when it goes through a proxy.
+    pub connect: Option<Duration>,
+    /// The TLS handshake, only for `https://` urls.
+    pub tls: Option<Duration>,
+    /// Sending the request until the response headers arrived.
+    pub ttfb: Option<Duration>,
+    /// Reading the response body.
+    pub body: Option<Duration>,
+}
+
+/// Response is the status and body returned by the server.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    pub status: StatusCode,
+    pub body: Bytes,
+}
+
+/// TlsOptions are the TLS settings of `https://` requests, by default the server certificate is
+/// verified against the system roots.
+#[derive(Clone, Default)]
+pub struct TlsOptions {
+    /// Root certificates trusted in addition to the system roots.
+    pub root_certificates: Vec<native_tls::Certificate>,
+    /// The client certificate and key presented to servers requiring mutual TLS.
+    pub identity: Option<native_tls::Identity>,
+    /// The name sent as SNI and verified against the certificate instead of the url host.
+    pub server_name: Option<String>,
+    pub min_version: Option<TlsVersion>,
+    /// Accept any certificate and hostname, the connection is encrypted but not authenticated.
+    pub insecure_skip_verify: bool,
+}
+
+/// TlsVersion is a version of the TLS protocol, written as `1.0`, `1.1` or `1.2`.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(try_from = "TlsVersionValue")]
+pub enum TlsVersion {
+    Tls10,
+    Tls11,
+    Tls12,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TlsVersionValue {
+    Number(f64),
+    Name(String),
+}
+
+/// Client requests a single url, each request opens a new connection so every check pays for
+/// and times the DNS lookup, connect and TLS handshake rather than reusing a pooled connection.
+///
+/// Redirects are followed and the proxies set in the environment are used, as reqwest does.
+#[derive(Clone, Debug)]
+pub struct Client {
+    client: hyper::Client<Connector>,
+    timings: Arc<Mutex<Timings>>,
+    proxies: Proxies,
+    headers: HeaderMap,
+    url: Url,
+    timeout_ms: u64,
+}
+
+/// Connector opens the connections of the Client, recording how long each phase took.
+#[derive(Clone, Debug)]
+struct Connector {
+    tls: native_tls::TlsConnector,
+    /// The host of the url and the server name used for its TLS handshake instead.
+    server_name: Option<(String, String)>,
+    connect_timeout_ms: u64,
+    proxies: Proxies,
+    timings: Arc<Mutex<Timings>>,
+}
+
+/// Stream is a connection opened by the Connector.
+enum Stream {
+    Tcp { stream: TcpStream, is_proxy: bool },
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl TlsOptions {
+    /// Build the connector used for the TLS handshake of `https://` requests.
+    ///
+    /// returns: Result<native_tls::TlsConnector, Error>
+    pub fn connector(&self) -> Result<native_tls::TlsConnector, Error> {
+        Ok(self
+            .builder()
+            .request_alpns(&["h2", "http/1.1"])
+            .danger_accept_invalid_certs(self.insecure_skip_verify)
+            .danger_accept_invalid_hostnames(self.insecure_skip_verify)
+            .build()?)
+    }
+
+    /// Start a connector trusting the root certificates, presenting the identity and requiring
+    /// the minimum version, verification is left to the caller.
+    ///
+    /// returns: native_tls::TlsConnectorBuilder
+    pub(crate) fn builder(&self) -> native_tls::TlsConnectorBuilder {
+        let mut builder = native_tls::TlsConnector::builder();
+        for certificate in &self.root_certificates {
+            builder.add_root_certificate(certificate.clone());
+        }
+        if let Some(identity) = &self.identity {
+            builder.identity(identity.clone());
+        }
+        builder.min_protocol_version(self.min_version.map(native_tls::Protocol::from));
+
+        builder
+    }
+}
+
+impl fmt::Debug for TlsOptions {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        // Certificates and identities do not implement Debug, and keys should not be logged.
+        f.debug_struct("TlsOptions")
+            .field("root_certificates", &self.root_certificates.len())
+            .field("identity", &self.identity.is_some())
+            .field("server_name", &self.server_name)
+            .field("min_version", &self.min_version)
+            .field("insecure_skip_verify", &self.insecure_skip_verify)
+            .finish()
+    }
+}
+
+impl From<TlsVersion> for native_tls::Protocol {
+    fn from(version: TlsVersion) -> Self {
+        match version {
+            TlsVersion::Tls10 => native_tls::Protocol::Tlsv10,
+            TlsVersion::Tls11 => native_tls::Protocol::Tlsv11,
+            TlsVersion::Tls12 => native_tls::Protocol::Tlsv12,
+        }
+    }
+}
+
+impl FromStr for TlsVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1.0" => Ok(TlsVersion::Tls10),
+            "1.1" => Ok(TlsVersion::Tls11),
+            "1.2" => Ok(TlsVersion::Tls12),
+            _ => Err(format!(
+                "invalid tls version {}, expected 1.0, 1.1 or 1.2",
+                s
+            )),
+        }
+    }
+}
+
+impl TryFrom<TlsVersionValue> for TlsVersion {
+    type Error = String;
+
+    fn try_from(value: TlsVersionValue) -> Result<Self, Self::Error> {
+        match value {
+            TlsVersionValue::Number(number) => format!("{:.1}", number).parse(),
+            TlsVersionValue::Name(name) => name.parse(),
+        }
+    }
+}
+
+impl fmt::Display for TlsVersion {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self {
+            TlsVersion::Tls10 => write!(f, "1.0"),
+            TlsVersion::Tls11 => write!(f, "1.1"),
+            TlsVersion::Tls12 => write!(f, "1.2"),
+        }
+    }
+}
+
+/// Parse every certificate of a PEM bundle.
+///
+/// # Arguments
+///
+/// * `pem`: One or more PEM encoded certificates.
+///
+/// returns: Result<Vec<native_tls::Certificate>, Error>
+pub fn certificates_from_pem(pem: &[u8]) -> Result<Vec<native_tls::Certificate>, Error> {
+    let pem = String::from_utf8_lossy(pem);
+    let certificates = pem
+        .split_inclusive(PEM_CERTIFICATE_END)
+        .filter(|block| block.contains(PEM_CERTIFICATE_END))
+        .map(|block| native_tls::Certificate::from_pem(block.trim().as_bytes()))
+        .collect::<Result<Vec<_>, _>>()?;
+    if certificates.is_empty() {
+        return Err(Error::TlsCertificateInvalid {
+            reason: "no PEM certificate found".to_string(),
+        });
+    }
+
+    Ok(certificates)
+}
+
+impl Client {
+    /// Create a new Client for the url.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The `http://` or `https://` url to request.
+    /// * `headers`: The headers sent with every request, including the user agent.
+    /// * `connect_timeout_ms`: Connect timeout in milliseconds to be considered a failure.
+    /// * `timeout_ms`: Timeout in milliseconds for the whole request to be considered a failure.
+    /// * `tls`: The TLS options of `https://` requests.
+    ///
+    /// returns: Result<Client, Error>
+    pub fn new(
+        url: &Url,
+        headers: HeaderMap,
+        connect_timeout_ms: u64,
+        timeout_ms: u64,
+        tls: &TlsOptions,
+    ) -> Result<Client, Error> {
+        Client::with_proxies(
+            url,
+            headers,
+            connect_timeout_ms,
+            timeout_ms,
+            tls,
+            Proxies::from_env(),
+        )
+    }
+
+    fn with_proxies(
+        url: &Url,
+        headers: HeaderMap,
+        connect_timeout_ms: u64,
+        timeout_ms: u64,
+        tls: &TlsOptions,
+        proxies: Proxies,
+    ) -> Result<Client, Error> {
+        let timings = Arc::new(Mutex::new(Timings::default()));
+        let connector = Connector {
+            tls: tls.connector()?,
+            server_name: tls.server_name.as_ref().map(|server_name| {
+                let host = url.host_str().unwrap_or_default();
+                (
+                    host.trim_start_matches('[')
+                        .trim_end_matches(']')
+                        .to_string(),
+                    server_name.clone(),
+                )
+            }),
+            connect_timeout_ms,
+            proxies: proxies.clone(),
+            timings: timings.clone(),
+        };
+
+        Ok(Client {
+            client: hyper::Client::builder()
+                .pool_max_idle_per_host(0)
+                .build(connector),
+            timings,
+            proxies,
+            headers,
+            url: url.clone(),
+            timeout_ms,
+        })
+    }
+
+    /// Send a request to the url and read the response, the body is cut off after
+    /// `MAX_BODY_BYTES`.
+    ///
+    /// # Arguments
+    ///
+    /// * `method`: The method of the request.
+    /// * `body`: The body of the request, if any.
+    /// * `timings`: Filled in with each phase as it completes, also when the request fails.
+    ///
+    /// returns: Result<Response, Error>
+    pub async fn send(
+        &self,
+        method: &Method,
+        body: Option<&Bytes>,
+        timings: &mut Timings,
+    ) -> Result<Response, Error> {
+        *self.timings.lock().unwrap() = Timings::default();
+        let result = time::timeout(
+            Duration::from_millis(self.timeout_ms),
+            self.exchange(method, body, timings),
+        )
+        .await;
+
+        // The connection phases are summed over every redirect.
+        let phases = *self.timings.lock().unwrap();
+        timings.dns = phases.dns;
+        timings.connect = phases.connect;
+        timings.tls = phases.tls;
+
+        result.map_err(|_| Error::TimeoutError {
+            timeout_ms: self.timeout_ms,
+        })?
+    }
+
+    async fn exchange(
+        &self,
+        method: &Method,
+        body: Option<&Bytes>,
+        timings: &mut Timings,
+    ) -> Result<Response, Error> {
+        let started = Instant::now();
+        let response = self.follow(method, body).await?;
+        let phases = *self.timings.lock().unwrap();
+        let connecting = [phases.dns, phases.connect, phases.tls]
+            .into_iter()
+            .flatten()
+            .sum();
+        timings.ttfb = Some(started.elapsed().saturating_sub(connecting));
+
+        let started = Instant::now();
+        let status = response.status();
+        let mut stream = response.into_body();
+        let mut body = Vec::new();
+        while let Some(chunk) = stream.data().await {
+            let chunk = chunk?;
+            let remaining = MAX_BODY_BYTES - body.len();
+            body.extend_from_slice(&chunk[..chunk.len().min(remaining)]);
+            if body.len() == MAX_BODY_BYTES {
+                break;
+            }
+        }
+        timings.body = Some(started.elapsed());
+
+        Ok(Response {
+            status,
+            body: Bytes::from(body),
+        })
+    }
+
+    /// Send the request and follow up to `MAX_REDIRECTS` redirects the way reqwest does.
+    async fn follow(
+        &self,
+        method: &Method,
+        body: Option<&Bytes>,
+    ) -> Result<hyper::Response<Body>, Error> {
+        let mut method = method.clone();
+        let mut body = body.cloned();
+        let mut headers = self.headers.clone();
+        let mut url = self.url.clone();
+        url.set_fragment(None);
+
+        for _ in 0..=MAX_REDIRECTS {
+            let mut request = Request::builder()
+                .method(method.clone())
+                .uri(url.as_str())
+                .body(body.clone().map(Body::from).unwrap_or_else(Body::empty))?;
+            *request.headers_mut() = headers.clone();
+            // Requests sent to a proxy rather than tunnelled carry its credentials themselves.
+            if let Some(authorization) = self
+                .proxies
+                .get(url.scheme(), url.host_str().unwrap_or_default())
+                .filter(|_| url.scheme() != SCHEME_HTTPS)
+                .and_then(|proxy| proxy.authorization.as_deref())
+            {
+                request.headers_mut().insert(
+                    PROXY_AUTHORIZATION,
+                    HeaderValue::from_str(authorization).map_err(|_| Error::ProxyTunnelFailed {
+                        reason: "invalid proxy credentials".to_string(),
+                    })?,
+                );
+            }
+
+            let response = self.client.request(request).await?;
+            let status = response.status();
+            let next = match status {
+                StatusCode::MOVED_PERMANENTLY
+                | StatusCode::FOUND
+                | StatusCode::SEE_OTHER
+                | StatusCode::TEMPORARY_REDIRECT
+                | StatusCode::PERMANENT_REDIRECT => response
+                    .headers()
+                    .get(LOCATION)
+                    .and_then(|location| location.to_str().ok())
+                    .and_then(|location| url.join(location).ok())
+                    .filter(|next| matches!(next.scheme(), "http" | SCHEME_HTTPS)),
+                _ => None,
+            };
+            let Some(mut next) = next else {
+                return Ok(response);
+            };
+            next.set_fragment(None);
+
+            if matches!(
+                status,
+                StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND | StatusCode::SEE_OTHER
+            ) {
+                body = None;
+                for header in [
+                    CONTENT_TYPE,
+                    CONTENT_LENGTH,
+                    CONTENT_ENCODING,
+                    TRANSFER_ENCODING,
+                ] {
+                    headers.remove(header);
+                }
+                if method != Method::HEAD {
+                    method = Method::GET;
+                }
+            }
+            if (next.host_str(), next.port_or_known_default())
+                != (url.host_str(), url.port_or_known_default())
+            {
+                for header in [AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION, WWW_AUTHENTICATE] {
+                    headers.remove(header);
+                }
+            }
+            url = next;
+        }
+
+        Err(Error::TooManyRedirects { max: MAX_REDIRECTS })
+    }
+}
+
+impl Connector {
+    async fn connect(
+        self,
+        uri: Uri,
+    ) -> Result<Stream, Error> {
+        let is_https = uri.scheme_str() == Some(SCHEME_HTTPS);
+        let host = uri
+            .host()
+            .filter(|host| !host.is_empty())
+            .ok_or_else(|| Error::InvalidUrl {
+                url: uri.to_string(),
+                reason: "missing host".to_string(),
+            })?
+            .trim_start_matches('[')
+            .trim_end_matches(']');
+        let port = uri.port_u16().unwrap_or(match is_https {
+            true => DEFAULT_HTTPS_PORT,
+            false => DEFAULT_HTTP_PORT,
+        });
+        let proxy = self.proxies.get(uri.scheme_str().unwrap_or_default(), host);
+        let (addr_host, addr_port) = match proxy {
+            Some(proxy) => (proxy.host.as_str(), proxy.port),
+            None => (host, port),
+        };
+
+        let started = Instant::now();
+        let addrs = lookup_host((addr_host, addr_port))
+            .await?
+            .collect::<Vec<SocketAddr>>();
+        self.record(|timings| &mut timings.dns, started);
+
+        let started = Instant::now();
+        let mut stream = time::timeout(
+            Duration::from_millis(self.connect_timeout_ms),
+            TcpStream::connect(addrs.as_slice()),
+        )
+        .await
+        .map_err(|_| Error::TimeoutError {
+            timeout_ms: self.connect_timeout_ms,
+        })??;
+        if let (Some(proxy), true) = (proxy, is_https) {
+            proxy.tunnel(&mut stream, host, port).await?;
+        }
+        self.record(|timings| &mut timings.connect, started);
+
+        if !is_https {
+            return Ok(Stream::Tcp {
+                stream,
+                is_proxy: proxy.is_some(),
+            });
+        }
+
+        let started = Instant::now();
+        let server_name = match &self.server_name {
+            Some((original, server_name)) if original == host => server_name.as_str(),
+            _ => host,
+        };
+        let stream = tokio_native_tls::TlsConnector::from(self.tls.clone())
+            .connect(server_name, stream)
+            .await?;
+        self.record(|timings| &mut timings.tls, started);
+
+        Ok(Stream::Tls(Box::new(stream)))
+    }
+
+    /// Add the time since `started` to a phase, a phase is repeated for each redirect.
+    fn record(
+        &self,
+        phase: fn(&mut Timings) -> &mut Option<Duration>,
+        started: Instant,
+    ) {
+        let mut timings = self.timings.lock().unwrap();
+        let phase = phase(&mut timings);
+        *phase = Some(phase.unwrap_or_default() + started.elapsed());
+    }
+}
+
+impl Service<Uri> for Connector {
+    type Response = Stream;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Stream, Error>> + Send>>;
+
+    fn poll_ready(
+        &mut self,
+        _: &mut Context<'_>,
+    ) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(
+        &mut self,
+        uri: Uri,
+    ) -> Self::Future {
+        Box::pin(self.clone().connect(uri))
+    }
+}
+
+impl Connection for Stream {
+    fn connected(&self) -> Connected {
+        match self {
+            // Requests sent to a proxy use the absolute url as their target.
+            Stream::Tcp { is_proxy, .. } => Connected::new().proxy(*is_proxy),
+            Stream::Tls(stream) => match stream.get_ref().negotiated_alpn() {
+                Ok(Some(protocol)) if protocol == b"h2" => Connected::new().negotiated_h2(),
+                _ => Connected::new(),
+            },
+        }
+    }
+}
+
+impl AsyncRead for Stream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Tcp { stream, .. } => Pin::new(stream).poll_read(cx, buf),
+            Stream::Tls(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Stream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Stream::Tcp { stream, .. } => Pin::new(stream).poll_write(cx, buf),
+            Stream::Tls(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Tcp { stream, .. } => Pin::new(stream).poll_flush(cx),
+            Stream::Tls(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Tcp { stream, .. } => Pin::new(stream).poll_shutdown(cx),
+            Stream::Tls(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+    use pretty_assertions::assert_eq;
+    use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa};
+    use reqwest::header::{HeaderValue, USER_AGENT};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    fn ca() -> rcgen::Certificate {
+        let mut params = CertificateParams::new(vec![]);
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "netcheck test ca");
+        rcgen::Certificate::from_params(params).unwrap()
+    }
+
+    /// Start an https server for `localhost` presenting a certificate signed by the CA, which
+    /// answers every request with `200 ok`.
+    async fn https_server(ca: &rcgen::Certificate) -> SocketAddr {
+        let cert =
+            rcgen::Certificate::from_params(CertificateParams::new(vec!["localhost".to_string()]))
+                .unwrap();
+        let identity = native_tls::Identity::from_pkcs8(
+            cert.serialize_pem_with_signer(ca).unwrap().as_bytes(),
+            cert.serialize_private_key_pem().as_bytes(),
+        )
+        .unwrap();
+        let acceptor = tokio_native_tls::TlsAcceptor::from(
+            native_tls::TlsAcceptor::builder(identity).build().unwrap(),
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let Ok(mut stream) = acceptor.accept(stream).await else {
+                        return;
+                    };
+                    let mut request = vec![];
+                    let mut buf = [0; 1024];
+                    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                        match stream.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let _ = stream
+                        .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok")
+                        .await;
+                    let _ = stream.shutdown().await;
+                });
+            }
+        });
+
+        addr
+    }
+
+    /// Create a client for the url which ignores the proxies of the environment.
+    fn client(
+        url: &str,
+        tls: &TlsOptions,
+    ) -> Result<Client, Error> {
+        Client::with_proxies(
+            &Url::parse(url).unwrap(),
+            HeaderMap::new(),
+            1000,
+            1000,
+            tls,
+            Proxies::default(),
+        )
+    }
+
+    async fn send(
+        url: &str,
+        tls: &TlsOptions,
+    ) -> Result<Response, Error> {
+        client(url, tls)?
+            .send(&Method::GET, None, &mut Timings::default())
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_client_send() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/health")
+                .query_param("verbose", "1")
+                .header("user-agent", "netcheck-test")
+                .header("content-length", "7")
+                .body("{\"a\":1}");
+            then.status(404).body("not here");
+        });
+
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, HeaderValue::from_static("netcheck-test"));
+        let client = Client::with_proxies(
+            &Url::parse(&format!(
+                "http://localhost:{}/health?verbose=1",
+                server.port()
+            ))
+            .unwrap(),
+            headers,
+            1000,
+            1000,
+            &TlsOptions::default(),
+            Proxies::default(),
+        )
+        .unwrap();
+        let mut timings = Timings::default();
+        let response = client
+            .send(&Method::POST, Some(&Bytes::from("{\"a\":1}")), &mut timings)
+            .await
+            .unwrap();
+
+        mock.assert();
+        assert_eq!(response.status, StatusCode::NOT_FOUND);
+        assert_eq!(response.body, Bytes::from("not here"));
+        assert!(timings.dns.is_some());
+        assert!(timings.connect.is_some());
+        assert_eq!(timings.tls, None);
+        assert!(timings.ttfb.is_some());
+        assert!(timings.body.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_client_send_private_ca() {
+        let ca = ca();
+        let addr = https_server(&ca).await;
+        let localhost = format!("https://localhost:{}/", addr.port());
+        let ip = format!("https://127.0.0.1:{}/", addr.port());
+
+        assert!(matches!(
+            send(&localhost, &TlsOptions::default()).await,
+            Err(Error::HyperError { .. })
+        ));
+
+        let mut tls = TlsOptions {
+            root_certificates: certificates_from_pem(ca.serialize_pem().unwrap().as_bytes())
+                .unwrap(),
+            min_version: Some(TlsVersion::Tls12),
+            ..TlsOptions::default()
+        };
+        let mut timings = Timings::default();
+        let response = client(&localhost, &tls)
+            .unwrap()
+            .send(&Method::GET, None, &mut timings)
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body, Bytes::from("ok"));
+        assert!(timings.connect.is_some());
+        assert!(timings.tls.is_some());
+
+        // The certificate is only valid for localhost, unless it is sent as the server name.
+        assert!(matches!(
+            send(&ip, &tls).await,
+            Err(Error::HyperError { .. })
+        ));
+        tls.server_name = Some("localhost".to_string());
+        assert_eq!(send(&ip, &tls).await.unwrap().status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_client_send_insecure_skip_verify() {
+        let addr = https_server(&ca()).await;
+        let response = send(
+            &format!("https://127.0.0.1:{}/", addr.port()),
+            &TlsOptions {
+                insecure_skip_verify: true,
+                ..TlsOptions::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+    }
+
+    #[test]
+    fn test_certificates_from_pem() {
+        let bundle = format!(
+            "{}\n{}",
+            ca().serialize_pem().unwrap(),
+            ca().serialize_pem().unwrap()
+        );
+        assert_eq!(certificates_from_pem(bundle.as_bytes()).unwrap().len(), 2);
+        assert!(matches!(
+            certificates_from_pem(b"not a certificate"),
+            Err(Error::TlsCertificateInvalid { .. })
+        ));
+    }
+
+    #[test]
+    fn test_tls_version_deserialize() {
+        let versions: Vec<TlsVersion> = serde_yaml::from_str("[1.0, \"1.1\", 1.2]").unwrap();
+        assert_eq!(
+            versions,
+            vec![TlsVersion::Tls10, TlsVersion::Tls11, TlsVersion::Tls12]
+        );
+        assert_eq!(TlsVersion::Tls12.to_string(), "1.2");
+        assert_eq!(
+            "1.3".parse::<TlsVersion>(),
+            Err("invalid tls version 1.3, expected 1.0, 1.1 or 1.2".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_client_send_refused() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let mut timings = Timings::default();
+        let result = client(
+            &format!("http://localhost:{}/", addr.port()),
+            &TlsOptions::default(),
+        )
+        .unwrap()
+        .send(&Method::GET, None, &mut timings)
+        .await;
+
+        assert!(matches!(result, Err(Error::HyperError { .. })));
+        assert!(timings.dns.is_some());
+        assert_eq!(timings.connect, None);
+        assert_eq!(timings.ttfb, None);
+    }
+
+    #[tokio::test]
+    async fn test_client_send_redirect() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST).path("/old");
+            then.status(303).header("location", "/new#top");
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/new");
+            then.status(200).body("moved");
+        });
+        server.mock(|when, then| {
+            when.path("/loop");
+            then.status(307).header("location", "/loop");
+        });
+
+        let mut timings = Timings::default();
+        let response = client(&server.url("/old"), &TlsOptions::default())
+            .unwrap()
+            .send(&Method::POST, Some(&Bytes::from("form")), &mut timings)
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body, Bytes::from("moved"));
+        assert!(timings.connect.is_some());
+
+        assert!(matches!(
+            send(&server.url("/loop"), &TlsOptions::default()).await,
+            Err(Error::TooManyRedirects { max: MAX_REDIRECTS })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_client_send_http_proxy() {
+        let proxy = MockServer::start();
+        let mock = proxy.mock(|when, then| {
+            when.path("/health")
+                .header("host", "api.invalid")
+                .header("proxy-authorization", "Basic dTpw");
+            then.status(200).body("proxied");
+        });
+        let proxies = Proxies::from_vars(|key| {
+            (key == "HTTP_PROXY").then(|| format!("http://u:p@{}", proxy.address()))
+        });
+
+        let mut timings = Timings::default();
+        let response = Client::with_proxies(
+            &Url::parse("http://api.invalid/health").unwrap(),
+            HeaderMap::new(),
+            1000,
+            1000,
+            &TlsOptions::default(),
+            proxies,
+        )
+        .unwrap()
+        .send(&Method::GET, None, &mut timings)
+        .await
+        .unwrap();
+
+        mock.assert();
+        assert_eq!(response.body, Bytes::from("proxied"));
+        assert!(timings.connect.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_client_send_https_proxy_tunnel() {
+        let ca = ca();
+        let server = https_server(&ca).await;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![];
+            let mut buf = [0; 1024];
+            while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            assert!(request.starts_with(format!("CONNECT localhost:{} ", server.port()).as_bytes()));
+            let mut upstream = tokio::net::TcpStream::connect(server).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                .await
+                .unwrap();
+            let _ = tokio::io::copy_bidirectional(&mut stream, &mut upstream).await;
+        });
+        let proxies = Proxies::from_vars(|key| (key == "HTTPS_PROXY").then(|| proxy.to_string()));
+
+        let mut timings = Timings::default();
+        let response = Client::with_proxies(
+            &Url::parse(&format!("https://localhost:{}/", server.port())).unwrap(),
+            HeaderMap::new(),
+            1000,
+            1000,
+            &TlsOptions {
+                root_certificates: certificates_from_pem(ca.serialize_pem().unwrap().as_bytes())
+                    .unwrap(),
+                ..TlsOptions::default()
+            },
+            proxies,
+        )
+        .unwrap()
+        .send(&Method::GET, None, &mut timings)
+        .await
+        .unwrap();
+
+        assert_eq!(response.body, Bytes::from("ok"));
+        assert!(timings.connect.is_some());
+        assert!(timings.tls.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_client_send_body_limit() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.path("/large");
+            then.status(200).body(vec![b'a'; MAX_BODY_BYTES + 1024]);
+        });
+
+        let response = send(&server.url("/large"), &TlsOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(response.body.len(), MAX_BODY_BYTES);
+    }
+}
@@ -7,10 +7,16 @@ pub const METRIC_LABEL_URL: &str = "url";
 pub const METRIC_LABEL_URLS: &str = "urls";
 pub const METRIC_LABEL_RUNNER_VERSION: &str = "runner_version";
 pub const METRIC_LABEL_RUNNER_STARTED_AT: &str = "started_at";
+pub const METRIC_LABEL_ISSUER: &str = "issuer";
 
 pub const METRIC_VALUE_AVAILABLE_TO_UNAVAILABLE: &str = "available_to_unavailable";
 pub const METRIC_VALUE_UNAVAILABLE_TO_AVAILABLE: &str = "unavailable_to_available";
+pub const METRIC_VALUE_AVAILABLE_TO_DEGRADED: &str = "available_to_degraded";
+pub const METRIC_VALUE_DEGRADED_TO_AVAILABLE: &str = "degraded_to_available";
+pub const METRIC_VALUE_DEGRADED_TO_UNAVAILABLE: &str = "degraded_to_unavailable";
+pub const METRIC_VALUE_UNAVAILABLE_TO_DEGRADED: &str = "unavailable_to_degraded";
 pub const METRIC_VALUE_AVAILABLE: &str = "available";
+pub const METRIC_VALUE_DEGRADED: &str = "degraded";
 pub const METRIC_VALUE_UNAVAILABLE: &str = "unavailable";
 
 #[derive(Clone, Debug)]
@@ -19,7 +25,29 @@ pub struct Metrics {
     pub events: Counter<u64>,
     pub requests: Counter<u64>,
     pub target_status: ObservableGauge<u64>,
+    pub url_status: ObservableGauge<u64>,
     pub requests_response_time_ns: Histogram<f64>,
+    pub phases: PhaseMetrics,
+    pub tls: TlsMetrics,
+}
+
+/// PhaseMetrics holds the histograms of how long each phase of an HTTP request took.
+#[derive(Clone, Debug)]
+pub struct PhaseMetrics {
+    pub dns_time_ns: Histogram<f64>,
+    pub connect_time_ns: Histogram<f64>,
+    pub tls_time_ns: Histogram<f64>,
+    pub time_to_first_byte_ns: Histogram<f64>,
+    pub body_time_ns: Histogram<f64>,
+}
+
+/// TlsMetrics holds the gauges reported for the certificates seen by `tls://` urls.
+#[derive(Clone, Debug)]
+pub struct TlsMetrics {
+    pub expiry_days: ObservableGauge<f64>,
+    pub san_match: ObservableGauge<u64>,
+    pub chain_valid: ObservableGauge<u64>,
+    pub degraded: ObservableGauge<u64>,
 }
 
 impl Default for Metrics {
@@ -47,11 +75,81 @@ impl Default for Metrics {
                 .with_description("The status of the target")
                 .with_unit(Unit::new("count"))
                 .init(),
+            url_status: meter
+                .u64_observable_gauge("runner_url_status")
+                .with_description("The status of each url of the target")
+                .with_unit(Unit::new("count"))
+                .init(),
             requests_response_time_ns: meter
                 .f64_histogram("runner_requests_response_time_ns")
                 .with_description("The time taken to get a response from a request")
                 .with_unit(Unit::new("ns"))
                 .init(),
+            phases: PhaseMetrics::default(),
+            tls: TlsMetrics::default(),
+        }
+    }
+}
+
+impl Default for PhaseMetrics {
+    fn default() -> Self {
+        let meter = global::meter("netcheck_runner");
+
+        PhaseMetrics {
+            dns_time_ns: meter
+                .f64_histogram("runner_requests_dns_time_ns")
+                .with_description("The time taken to resolve the host of a request")
+                .with_unit(Unit::new("ns"))
+                .init(),
+            connect_time_ns: meter
+                .f64_histogram("runner_requests_connect_time_ns")
+                .with_description("The time taken to open the connection of a request")
+                .with_unit(Unit::new("ns"))
+                .init(),
+            tls_time_ns: meter
+                .f64_histogram("runner_requests_tls_time_ns")
+                .with_description("The time taken by the TLS handshake of a request")
+                .with_unit(Unit::new("ns"))
+                .init(),
+            time_to_first_byte_ns: meter
+                .f64_histogram("runner_requests_time_to_first_byte_ns")
+                .with_description("The time from sending a request until the response headers")
+                .with_unit(Unit::new("ns"))
+                .init(),
+            body_time_ns: meter
+                .f64_histogram("runner_requests_body_time_ns")
+                .with_description("The time taken to read the response body of a request")
+                .with_unit(Unit::new("ns"))
+                .init(),
+        }
+    }
+}
+
+impl Default for TlsMetrics {
+    fn default() -> Self {
+        let meter = global::meter("netcheck_runner");
+
+        TlsMetrics {
+            expiry_days: meter
+                .f64_observable_gauge("runner_tls_certificate_expiry_days")
+                .with_description("The days until the presented certificate expires")
+                .with_unit(Unit::new("d"))
+                .init(),
+            san_match: meter
+                .u64_observable_gauge("runner_tls_certificate_san_match")
+                .with_description("If the presented certificate matches the host")
+                .with_unit(Unit::new("count"))
+                .init(),
+            chain_valid: meter
+                .u64_observable_gauge("runner_tls_certificate_chain_valid")
+                .with_description("If the presented certificate chain is trusted")
+                .with_unit(Unit::new("count"))
+                .init(),
+            degraded: meter
+                .u64_observable_gauge("runner_tls_degraded")
+                .with_description("If the presented certificate expires within the threshold")
+                .with_unit(Unit::new("count"))
+                .init(),
         }
     }
 }
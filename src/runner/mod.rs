@@ -1,12 +1,33 @@
+pub use self::events::RunnerEvent;
+pub use self::expect::{BodyAssertion, Expectations, StatusRange};
+pub use self::http::{certificates_from_pem, Timings, TlsOptions, TlsVersion};
+pub use self::probe::{Probe, ProbeResult};
+pub use self::registry::{Registry, TargetReport, UrlReport};
 pub use self::runner::Error;
 pub use self::runner::Runner;
 pub use self::runner::RunnerBuilder;
 pub use self::status::Event;
+pub use self::status::Quorum;
+pub use self::status::State;
 pub use self::status::Status;
-pub use self::target::Target;
+pub use self::status::Strategy;
+pub use self::status::TargetStatus;
+pub use self::target::{
+    parse_url, validate_names, Target, TargetParseError, DEFAULT_SCHEME, SUPPORTED_SCHEMES,
+};
 
+mod dns;
+mod events;
+mod expect;
+mod http;
 mod metric;
+mod probe;
+mod proxy;
+mod registry;
+#[allow(clippy::module_inception)]
 mod runner;
 mod status;
 mod target;
+mod tcp;
+mod tls;
 mod url;
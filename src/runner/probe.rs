@@ -0,0 +1,326 @@
+use std::fmt::Debug;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use hyper::body::Bytes;
+use opentelemetry::KeyValue;
+use reqwest::{Method, Url};
+use tracing::warn;
+
+use crate::runner::metric::{
+    TlsMetrics, METRIC_LABEL_ISSUER, METRIC_LABEL_TARGET_NAME, METRIC_LABEL_URL,
+};
+use crate::runner::{dns, http, tcp, tls, Error, Expectations, Timings, TlsOptions};
+
+/// ProbeResult is the outcome of a single check of a url.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProbeResult {
+    pub success: bool,
+    pub latency: Duration,
+    pub error: Option<String>,
+    /// How long each phase of the check took, for probes which can tell.
+    pub timings: Option<Timings>,
+    /// The check succeeded but found a problem, the url is treated as degraded as if it was
+    /// slow.
+    pub degraded: bool,
+}
+
+impl ProbeResult {
+    /// A successful check which took `latency`.
+    pub fn ok(latency: Duration) -> ProbeResult {
+        ProbeResult {
+            success: true,
+            latency,
+            error: None,
+            timings: None,
+            degraded: false,
+        }
+    }
+
+    /// A failed check which took `latency`, with the reason it failed.
+    pub fn err(
+        latency: Duration,
+        error: impl ToString,
+    ) -> ProbeResult {
+        ProbeResult {
+            success: false,
+            latency,
+            error: Some(error.to_string()),
+            timings: None,
+            degraded: false,
+        }
+    }
+
+    /// Attach the timing of each phase of the check.
+    pub fn with_timings(
+        mut self,
+        timings: Timings,
+    ) -> ProbeResult {
+        self.timings = Some(timings);
+        self
+    }
+
+    /// Mark the check as degraded.
+    pub fn with_degraded(
+        mut self,
+        degraded: bool,
+    ) -> ProbeResult {
+        self.degraded = degraded;
+        self
+    }
+
+    /// The result of a check started at `started`.
+    pub fn from_result<E: ToString>(
+        started: Instant,
+        result: Result<(), E>,
+    ) -> ProbeResult {
+        match result {
+            Ok(()) => ProbeResult::ok(started.elapsed()),
+            Err(e) => ProbeResult::err(started.elapsed(), e),
+        }
+    }
+}
+
+/// Probe checks a single url of a target, the Runner drives it every interval and tracks the
+/// status of the url from its results.
+///
+/// The Runner picks a probe from the scheme of each url, a custom probe can be registered for a
+/// url with `RunnerBuilder::probe`.
+///
+/// # Example
+///
+/// ```
+/// # use std::time::{Duration, Instant};
+/// # use async_trait::async_trait;
+/// # use netcheck::runner::{Probe, ProbeResult, RunnerBuilder, Target};
+/// # use reqwest::Url;
+/// #[derive(Debug)]
+/// struct Queue;
+///
+/// #[async_trait]
+/// impl Probe for Queue {
+///     async fn probe(&self) -> ProbeResult {
+///         let started = Instant::now();
+///         // Check the queue here.
+///         ProbeResult::from_result(started, Ok::<(), String>(()))
+///     }
+/// }
+///
+/// let url = Url::parse("queue://jobs").unwrap();
+/// let runner = RunnerBuilder::new()
+///     .target(Target::new("jobs".to_string(), vec![url.clone()]))
+///     .probe(url, Queue)
+///     .build();
+/// ```
+#[async_trait]
+pub trait Probe: Debug + Send + Sync {
+    /// Check the url once.
+    async fn probe(&self) -> ProbeResult;
+}
+
+/// HttpProbe sends a request to the url, failing unless the response meets the expectations,
+/// and reports how long each phase of the request took.
+#[derive(Debug)]
+pub struct HttpProbe {
+    pub client: http::Client,
+    pub method: Method,
+    pub body: Option<Bytes>,
+    pub expectations: Expectations,
+}
+
+#[async_trait]
+impl Probe for HttpProbe {
+    async fn probe(&self) -> ProbeResult {
+        let started = Instant::now();
+        let mut timings = Timings::default();
+        let result = async {
+            let resp = self
+                .client
+                .send(&self.method, self.body.as_ref(), &mut timings)
+                .await?;
+
+            self.expectations.check(resp.status.as_u16(), &resp.body)
+        }
+        .await;
+
+        ProbeResult::from_result(started, result).with_timings(timings)
+    }
+}
+
+/// TcpProbe opens a raw TCP connection to a `tcp://host:port` url.
+#[derive(Debug)]
+pub struct TcpProbe {
+    pub url: Url,
+    pub connect_timeout_ms: u64,
+}
+
+#[async_trait]
+impl Probe for TcpProbe {
+    async fn probe(&self) -> ProbeResult {
+        let started = Instant::now();
+        ProbeResult::from_result(
+            started,
+            tcp::connect(&self.url, self.connect_timeout_ms).await,
+        )
+    }
+}
+
+/// DnsProbe resolves the lookup described by a `dns://` url.
+#[derive(Debug)]
+pub struct DnsProbe {
+    pub url: Url,
+    pub timeout_ms: u64,
+}
+
+#[async_trait]
+impl Probe for DnsProbe {
+    async fn probe(&self) -> ProbeResult {
+        let started = Instant::now();
+        ProbeResult::from_result(started, dns::resolve(&self.url, self.timeout_ms).await)
+    }
+}
+
+/// TlsProbe inspects the certificate presented by a `tls://` url, failing if a client using the
+/// TLS options would reject it and degraded if it expires within the threshold, and reports the
+/// certificate metrics.
+#[derive(Debug)]
+pub struct TlsProbe {
+    pub url: Url,
+    pub tls: TlsOptions,
+    pub connect_timeout_ms: u64,
+    pub timeout_ms: u64,
+    pub target: String,
+    pub metrics: TlsMetrics,
+}
+
+#[async_trait]
+impl Probe for TlsProbe {
+    async fn probe(&self) -> ProbeResult {
+        let started = Instant::now();
+        match self.check().await {
+            Ok(is_expiring) => ProbeResult::ok(started.elapsed()).with_degraded(is_expiring),
+            Err(e) => ProbeResult::err(started.elapsed(), e),
+        }
+    }
+}
+
+impl TlsProbe {
+    /// Check the certificate, returning if it expires within the threshold.
+    async fn check(&self) -> Result<bool, Error> {
+        let handshake = tls::Handshake::from_url(&self.url)?;
+        let certificate = handshake
+            .inspect(&self.tls, self.connect_timeout_ms, self.timeout_ms)
+            .await?;
+        let is_expiring = certificate.is_expiring(handshake.expiry_threshold_days);
+
+        self.update_metrics(&certificate, is_expiring);
+        if is_expiring {
+            warn!(
+                runner_target = self.target,
+                url = self.url.to_string(),
+                issuer = certificate.issuer,
+                days_until_expiry = certificate.days_until_expiry,
+                "certificate expires within {} days",
+                handshake.expiry_threshold_days
+            );
+        }
+
+        // Like `https://` urls, any certificate is accepted but still reported on.
+        if self.tls.insecure_skip_verify {
+            return Ok(is_expiring);
+        }
+
+        certificate.verify().map(|_| is_expiring)
+    }
+
+    /// Update the certificate metrics.
+    ///
+    /// # Arguments
+    ///
+    /// * `certificate`: The certificate presented during the handshake.
+    /// * `is_expiring`: If the certificate expires within the threshold.
+    fn update_metrics(
+        &self,
+        certificate: &tls::Certificate,
+        is_expiring: bool,
+    ) {
+        let labels = [
+            KeyValue::new(METRIC_LABEL_TARGET_NAME, self.target.clone()),
+            KeyValue::new(METRIC_LABEL_URL, self.url.to_string()),
+        ];
+
+        self.metrics.expiry_days.observe(
+            certificate.days_until_expiry,
+            &[
+                labels[0].clone(),
+                labels[1].clone(),
+                KeyValue::new(METRIC_LABEL_ISSUER, certificate.issuer.clone()),
+            ],
+        );
+        self.metrics
+            .san_match
+            .observe(if certificate.san_match { 1 } else { 0 }, &labels);
+        self.metrics
+            .chain_valid
+            .observe(if certificate.chain_valid { 1 } else { 0 }, &labels);
+        self.metrics
+            .degraded
+            .observe(if is_expiring { 1 } else { 0 }, &labels);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_http_probe() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.path("/ok");
+            then.status(200);
+        });
+        server.mock(|when, then| {
+            when.path("/missing");
+            then.status(404);
+        });
+
+        let probe = |path| HttpProbe {
+            client: http::Client::new(
+                &Url::parse(&server.url(path)).unwrap(),
+                Default::default(),
+                1000,
+                1000,
+                &Default::default(),
+            )
+            .unwrap(),
+            method: Method::GET,
+            body: None,
+            expectations: Expectations::default(),
+        };
+
+        let result = probe("/ok").probe().await;
+        assert_eq!(result.success, true);
+        assert_eq!(result.error, None);
+        assert!(result.timings.unwrap().ttfb.is_some());
+
+        let result = probe("/missing").probe().await;
+        assert_eq!(result.success, false);
+        assert_eq!(result.error, Some("404".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_tcp_probe() {
+        let result = TcpProbe {
+            url: Url::parse("tcp://127.0.0.1:1").unwrap(),
+            connect_timeout_ms: 1000,
+        }
+        .probe()
+        .await;
+        assert_eq!(result.success, false);
+        assert!(result.error.is_some());
+    }
+}
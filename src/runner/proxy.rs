@@ -0,0 +1,368 @@
+use std::env;
+use std::net::IpAddr;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use percent_encoding::percent_decode_str;
+use reqwest::Url;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::warn;
+
+use crate::runner::Error;
+
+const SCHEME_HTTP: &str = "http";
+const MAX_TUNNEL_RESPONSE_BYTES: usize = 8 * 1024;
+
+/// Proxies are the HTTP proxies set in the environment, read the way curl reads them:
+/// `HTTP_PROXY` for `http://` urls, `HTTPS_PROXY` for `https://` urls and `ALL_PROXY` for either
+/// when it is not set, also in lower case, skipping the hosts listed in `NO_PROXY`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Proxies {
+    http: Option<Proxy>,
+    https: Option<Proxy>,
+    no_proxy: Vec<NoProxy>,
+}
+
+/// Proxy is an `http://` proxy, `http://` requests are sent to it and `https://` requests are
+/// tunnelled through it with `CONNECT`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Proxy {
+    pub host: String,
+    pub port: u16,
+    /// The `Proxy-Authorization` header for the credentials of the proxy url.
+    pub authorization: Option<String>,
+}
+
+/// NoProxy is an entry of `NO_PROXY`: `*`, an IP address or network, or a domain which also
+/// matches its subdomains.
+#[derive(Clone, Debug, PartialEq)]
+enum NoProxy {
+    Any,
+    Network(IpAddr, u32),
+    Domain(String),
+}
+
+impl Proxies {
+    /// Read the proxies from the environment.
+    pub fn from_env() -> Proxies {
+        Proxies::from_vars(|key| env::var(key).ok())
+    }
+
+    /// Read the proxies from the variables.
+    ///
+    /// # Arguments
+    ///
+    /// * `var`: Looks up the value of a variable.
+    ///
+    /// returns: Proxies
+    pub(crate) fn from_vars(var: impl Fn(&str) -> Option<String>) -> Proxies {
+        let value = |key: &str| {
+            var(key)
+                .or_else(|| var(&key.to_lowercase()))
+                .filter(|value| !value.trim().is_empty())
+        };
+        let proxy = |key: &str| value(key).and_then(|value| Proxy::parse(&value));
+        let all = proxy("ALL_PROXY");
+
+        Proxies {
+            // In CGI a client sets HTTP_PROXY with the Proxy header, so it is not trusted there.
+            http: var("REQUEST_METHOD")
+                .is_none()
+                .then(|| proxy("HTTP_PROXY"))
+                .flatten()
+                .or_else(|| all.clone()),
+            https: proxy("HTTPS_PROXY").or(all),
+            no_proxy: value("NO_PROXY")
+                .map(|value| value.split(',').filter_map(NoProxy::parse).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// The proxy to connect through for a url, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `scheme`: The scheme of the url.
+    /// * `host`: The host of the url.
+    ///
+    /// returns: Option<&Proxy>
+    pub fn get(
+        &self,
+        scheme: &str,
+        host: &str,
+    ) -> Option<&Proxy> {
+        let host = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_lowercase();
+        if self.no_proxy.iter().any(|entry| entry.matches(&host)) {
+            return None;
+        }
+
+        match scheme {
+            SCHEME_HTTP => self.http.as_ref(),
+            _ => self.https.as_ref(),
+        }
+    }
+}
+
+impl Proxy {
+    /// Parse a proxy url, a url without a scheme is taken to be `http://`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value`: The proxy url.
+    ///
+    /// returns: Option<Proxy>
+    fn parse(value: &str) -> Option<Proxy> {
+        let value = value.trim();
+        let url = match value.contains("://") {
+            true => Url::parse(value),
+            false => Url::parse(&format!("{}://{}", SCHEME_HTTP, value)),
+        }
+        .ok()?;
+        if url.scheme() != SCHEME_HTTP {
+            warn!(
+                scheme = url.scheme(),
+                "ignoring proxy, only http:// proxies are supported"
+            );
+            return None;
+        }
+
+        let decode = |part: &str| percent_decode_str(part).decode_utf8_lossy().to_string();
+        let authorization = (!url.username().is_empty()).then(|| {
+            let credentials = format!(
+                "{}:{}",
+                decode(url.username()),
+                decode(url.password().unwrap_or_default())
+            );
+            format!("Basic {}", STANDARD.encode(credentials))
+        });
+
+        Some(Proxy {
+            host: url
+                .host_str()?
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_string(),
+            port: url.port_or_known_default()?,
+            authorization,
+        })
+    }
+
+    /// Open a tunnel to the host through the proxy with `CONNECT`.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream`: The connection to the proxy.
+    /// * `host`: The host to tunnel to.
+    /// * `port`: The port to tunnel to.
+    ///
+    /// returns: Result<(), Error>
+    pub async fn tunnel(
+        &self,
+        stream: &mut TcpStream,
+        host: &str,
+        port: u16,
+    ) -> Result<(), Error> {
+        let authority = match host.parse::<IpAddr>() {
+            Ok(IpAddr::V6(_)) => format!("[{}]:{}", host, port),
+            _ => format!("{}:{}", host, port),
+        };
+        let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", authority);
+        if let Some(authorization) = &self.authorization {
+            request.push_str(&format!("Proxy-Authorization: {}\r\n", authorization));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).await?;
+
+        let failed = |reason: String| Error::ProxyTunnelFailed { reason };
+        let mut response = vec![];
+        let mut buf = [0; 1024];
+        while !response.windows(4).any(|window| window == b"\r\n\r\n") {
+            let n = stream.read(&mut buf).await?;
+            if n == 0 {
+                return Err(failed("proxy closed the connection".to_string()));
+            }
+            response.extend_from_slice(&buf[..n]);
+            if response.len() > MAX_TUNNEL_RESPONSE_BYTES {
+                return Err(failed("proxy response too large".to_string()));
+            }
+        }
+
+        let response = String::from_utf8_lossy(&response);
+        let status = response.lines().next().unwrap_or_default();
+        match status.split_whitespace().nth(1) {
+            Some("200") if status.starts_with("HTTP/1.") => Ok(()),
+            _ => Err(failed(format!("proxy responded with {}", status))),
+        }
+    }
+}
+
+impl NoProxy {
+    fn parse(entry: &str) -> Option<NoProxy> {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            return None;
+        }
+        if entry == "*" {
+            return Some(NoProxy::Any);
+        }
+
+        let (addr, prefix) = match entry.split_once('/') {
+            Some((addr, prefix)) => (addr, prefix.parse().ok()),
+            None => (entry, None),
+        };
+        match addr
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+        {
+            Ok(ip) => {
+                let bits = if ip.is_ipv4() { 32 } else { 128 };
+                Some(NoProxy::Network(ip, prefix.unwrap_or(bits).min(bits)))
+            }
+            Err(_) => Some(NoProxy::Domain(
+                entry.trim_start_matches('.').to_lowercase(),
+            )),
+        }
+    }
+
+    fn matches(
+        &self,
+        host: &str,
+    ) -> bool {
+        match (self, host.parse::<IpAddr>()) {
+            (NoProxy::Any, _) => true,
+            (NoProxy::Network(IpAddr::V4(network), prefix), Ok(IpAddr::V4(ip))) => {
+                let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+                u32::from(*network) & mask == u32::from(ip) & mask
+            }
+            (NoProxy::Network(IpAddr::V6(network), prefix), Ok(IpAddr::V6(ip))) => {
+                let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+                u128::from(*network) & mask == u128::from(ip) & mask
+            }
+            (NoProxy::Domain(domain), Err(_)) => {
+                host == domain
+                    || host
+                        .strip_suffix(domain.as_str())
+                        .is_some_and(|rest| rest.ends_with('.'))
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    fn proxies(vars: &[(&str, &str)]) -> Proxies {
+        let vars = vars
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>();
+        Proxies::from_vars(|key| vars.get(key).cloned())
+    }
+
+    #[test]
+    fn test_proxies_from_vars() {
+        let proxies = proxies(&[
+            ("http_proxy", "proxy.internal:3128"),
+            ("ALL_PROXY", "http://user:p%40ss@[::1]:8080"),
+            ("NO_PROXY", "example.com, .internal,10.0.0.0/8,::1"),
+        ]);
+
+        assert_eq!(
+            proxies.get("http", "api.test"),
+            Some(&Proxy {
+                host: "proxy.internal".to_string(),
+                port: 3128,
+                authorization: None,
+            })
+        );
+        assert_eq!(
+            proxies.get("https", "api.test"),
+            Some(&Proxy {
+                host: "::1".to_string(),
+                port: 8080,
+                authorization: Some("Basic dXNlcjpwQHNz".to_string()),
+            })
+        );
+        for host in [
+            "example.com",
+            "www.Example.com",
+            "db.internal",
+            "10.1.2.3",
+            "[::1]",
+        ] {
+            assert_eq!(proxies.get("http", host), None, "{}", host);
+        }
+        for host in ["notexample.com", "11.0.0.1", "[::2]"] {
+            assert!(proxies.get("http", host).is_some(), "{}", host);
+        }
+    }
+
+    #[test]
+    fn test_proxies_from_vars_ignored() {
+        assert_eq!(proxies(&[]), Proxies::default());
+        assert_eq!(
+            proxies(&[("HTTPS_PROXY", "socks5://proxy:1080"), ("HTTP_PROXY", " ")]),
+            Proxies::default()
+        );
+        assert_eq!(
+            proxies(&[("HTTP_PROXY", "proxy:3128"), ("REQUEST_METHOD", "GET")]),
+            Proxies::default()
+        );
+        assert_eq!(
+            proxies(&[("HTTP_PROXY", "proxy:3128"), ("NO_PROXY", "*")]).get("http", "a.test"),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_proxy_tunnel() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for response in [
+                &b"HTTP/1.1 200 Connection established\r\n\r\n"[..],
+                b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n",
+            ] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = vec![];
+                let mut buf = [0; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                }
+                assert_eq!(
+                    String::from_utf8(request).unwrap(),
+                    "CONNECT example.com:443 HTTP/1.1\r\nHost: example.com:443\r\n\
+                     Proxy-Authorization: Basic dTpw\r\n\r\n"
+                );
+                stream.write_all(response).await.unwrap();
+            }
+        });
+
+        let proxy = Proxy::parse(&format!("http://u:p@{}", addr)).unwrap();
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        assert!(proxy.tunnel(&mut stream, "example.com", 443).await.is_ok());
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        assert_eq!(
+            proxy
+                .tunnel(&mut stream, "example.com", 443)
+                .await
+                .unwrap_err()
+                .to_string(),
+            "proxy tunnel failed: proxy responded with HTTP/1.1 407 Proxy Authentication Required"
+        );
+    }
+}
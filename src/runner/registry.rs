@@ -0,0 +1,260 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
+
+use crate::runner::{State, Status, Target, TargetStatus};
+
+/// Registry holds the latest report of every running target, shared between the runners and the
+/// status API.
+#[derive(Clone, Debug, Default)]
+pub struct Registry {
+    targets: Arc<RwLock<BTreeMap<String, TargetReport>>>,
+}
+
+/// TargetReport is a snapshot of the status of a target and each of its urls.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct TargetReport {
+    pub name: String,
+    pub is_unavailable: bool,
+    pub unavailable_started: Option<DateTime<Utc>>,
+    pub is_degraded: bool,
+    pub degraded_started: Option<DateTime<Utc>>,
+    pub unavailable_count: i32,
+    pub quorum: String,
+    pub available_urls: usize,
+    pub urls: Vec<UrlReport>,
+}
+
+/// UrlReport is a snapshot of the status of a single url of a target.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct UrlReport {
+    pub url: String,
+    pub is_unavailable: bool,
+    pub unavailable_started: Option<DateTime<Utc>>,
+    pub is_degraded: bool,
+    pub degraded_started: Option<DateTime<Utc>>,
+    pub unavailable_count: i32,
+    pub available_streak: u8,
+    pub unavailable_streak: u8,
+    pub last_checked: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub last_latency_ms: Option<f64>,
+}
+
+impl Registry {
+    /// Create a new empty Registry.
+    ///
+    /// returns: Registry
+    pub fn new() -> Registry {
+        Registry::default()
+    }
+
+    /// Replace the report of a target.
+    ///
+    /// # Arguments
+    ///
+    /// * `report`: The latest report of the target.
+    pub fn update(
+        &self,
+        report: TargetReport,
+    ) {
+        self.targets
+            .write()
+            .expect("Failed to lock the registry")
+            .insert(report.name.clone(), report);
+    }
+
+    /// Replace the report of a target unless its runner was told to stop. The token is checked
+    /// under the registry lock, so a report can not land after the target is removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `report`: The latest report of the target.
+    /// * `stop`: The token cancelled when the runner is stopped.
+    pub fn update_unless_cancelled(
+        &self,
+        report: TargetReport,
+        stop: &CancellationToken,
+    ) {
+        let mut targets = self.targets.write().expect("Failed to lock the registry");
+        if !stop.is_cancelled() {
+            targets.insert(report.name.clone(), report);
+        }
+    }
+
+    /// Remove the report of a target which is no longer running.
+    pub fn remove(
+        &self,
+        name: &str,
+    ) {
+        self.targets
+            .write()
+            .expect("Failed to lock the registry")
+            .remove(name);
+    }
+
+    /// The reports of every target, sorted by name.
+    pub fn targets(&self) -> Vec<TargetReport> {
+        self.targets
+            .read()
+            .expect("Failed to lock the registry")
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// The report of a single target.
+    pub fn target(
+        &self,
+        name: &str,
+    ) -> Option<TargetReport> {
+        self.targets
+            .read()
+            .expect("Failed to lock the registry")
+            .get(name)
+            .cloned()
+    }
+}
+
+impl TargetReport {
+    /// Create a report from the current status of a target.
+    ///
+    /// # Arguments
+    ///
+    /// * `target`: The target being checked.
+    /// * `status`: The status of the target and each of its urls.
+    ///
+    /// returns: TargetReport
+    pub fn new(
+        target: &Target,
+        status: &TargetStatus,
+    ) -> TargetReport {
+        TargetReport {
+            name: target.name.clone(),
+            is_unavailable: status.is_unavailable,
+            unavailable_started: status.is_unavailable.then_some(status.unavailable_started),
+            is_degraded: status.is_degraded,
+            degraded_started: status.is_degraded.then_some(status.degraded_started),
+            unavailable_count: status.unavailable_count,
+            quorum: status.quorum.to_string(),
+            available_urls: status.available(),
+            urls: target
+                .urls
+                .iter()
+                .zip(status.urls.iter())
+                .map(|(url, status)| UrlReport::new(url.as_str(), status))
+                .collect(),
+        }
+    }
+}
+
+impl UrlReport {
+    fn new(
+        url: &str,
+        status: &Status,
+    ) -> UrlReport {
+        UrlReport {
+            url: url.to_string(),
+            is_unavailable: status.is_unavailable,
+            unavailable_started: status.is_unavailable.then_some(status.unavailable_started),
+            is_degraded: status.state() == State::Degraded,
+            degraded_started: (status.state() == State::Degraded)
+                .then_some(status.degraded_started),
+            unavailable_count: status.unavailable_count,
+            available_streak: status.available_counted,
+            unavailable_streak: status.unavailable_counted,
+            last_checked: status.last_checked,
+            last_error: status.last_error.clone(),
+            last_latency_ms: status.last_latency_ms,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use reqwest::Url;
+
+    use super::*;
+    use crate::runner::Quorum;
+
+    fn target() -> Target {
+        Target::new(
+            "mirrors".to_string(),
+            vec![
+                Url::parse("https://one.example.com").unwrap(),
+                Url::parse("https://two.example.com").unwrap(),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_target_report_new() {
+        let mut status = TargetStatus::new(Quorum::Any, vec![Status::new(1, 1), Status::new(1, 1)]);
+        status.urls[1].handle_unavailable();
+        status.urls[1].last_error = Some("connection refused".to_string());
+        status.urls[1].last_latency_ms = Some(1.5);
+        status.evaluate();
+
+        let report = TargetReport::new(&target(), &status);
+        assert_eq!(report.name, "mirrors");
+        assert_eq!(report.is_unavailable, false);
+        assert_eq!(report.unavailable_started, None);
+        assert_eq!(report.quorum, "any");
+        assert_eq!(report.available_urls, 1);
+        assert_eq!(report.urls[0].url, "https://one.example.com/");
+        assert_eq!(report.urls[0].is_unavailable, false);
+        assert_eq!(report.urls[1].is_unavailable, true);
+        assert!(report.urls[1].unavailable_started.is_some());
+        assert_eq!(report.urls[1].unavailable_streak, 1);
+        assert_eq!(
+            report.urls[1].last_error,
+            Some("connection refused".to_string())
+        );
+        assert_eq!(report.urls[1].last_latency_ms, Some(1.5));
+        assert_eq!(report.is_degraded, false);
+
+        status.urls[0].handle_slow();
+        status.evaluate();
+        let report = TargetReport::new(&target(), &status);
+        assert_eq!(report.is_degraded, true);
+        assert!(report.degraded_started.is_some());
+        assert_eq!(report.urls[0].is_degraded, true);
+        assert_eq!(report.urls[1].is_degraded, false);
+    }
+
+    #[test]
+    fn test_registry() {
+        let registry = Registry::new();
+        let status = TargetStatus::new(Quorum::Any, vec![Status::new(1, 1), Status::new(1, 1)]);
+        registry.update(TargetReport::new(&target(), &status));
+
+        assert_eq!(registry.targets().len(), 1);
+        assert_eq!(
+            registry.target("mirrors").map(|report| report.urls.len()),
+            Some(2)
+        );
+        assert_eq!(registry.target("missing"), None);
+
+        registry.remove("mirrors");
+        assert_eq!(registry.targets(), vec![]);
+    }
+
+    #[test]
+    fn test_registry_update_unless_cancelled() {
+        let registry = Registry::new();
+        let status = TargetStatus::new(Quorum::Any, vec![Status::new(1, 1), Status::new(1, 1)]);
+        let stop = CancellationToken::new();
+
+        registry.update_unless_cancelled(TargetReport::new(&target(), &status), &stop);
+        assert!(registry.target("mirrors").is_some());
+
+        stop.cancel();
+        registry.remove("mirrors");
+        registry.update_unless_cancelled(TargetReport::new(&target(), &status), &stop);
+        assert_eq!(registry.target("mirrors"), None);
+    }
+}
@@ -1,21 +1,38 @@
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use futures_util::stream::{self, StreamExt};
+use hyper::body::Bytes;
 use opentelemetry::KeyValue;
-use reqwest::{Client, Url};
+use rand::Rng;
+use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
+use reqwest::{Method, Url};
 use thiserror::Error;
+use tokio::sync::broadcast;
+use tokio::time::{Interval, MissedTickBehavior};
 use tokio::{task, time};
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info};
 
 use crate::built_info;
+use crate::notifier::{Notification, Notifier};
+use crate::runner::events::DEFAULT_EVENTS_CAPACITY;
 use crate::runner::metric::{
     Metrics, METRIC_LABEL_RUNNER_STARTED_AT, METRIC_LABEL_RUNNER_VERSION, METRIC_LABEL_STATUS,
     METRIC_LABEL_TARGET_NAME, METRIC_LABEL_URL, METRIC_LABEL_URLS, METRIC_VALUE_AVAILABLE,
-    METRIC_VALUE_AVAILABLE_TO_UNAVAILABLE, METRIC_VALUE_UNAVAILABLE,
-    METRIC_VALUE_UNAVAILABLE_TO_AVAILABLE,
+    METRIC_VALUE_AVAILABLE_TO_DEGRADED, METRIC_VALUE_AVAILABLE_TO_UNAVAILABLE,
+    METRIC_VALUE_DEGRADED, METRIC_VALUE_DEGRADED_TO_AVAILABLE,
+    METRIC_VALUE_DEGRADED_TO_UNAVAILABLE, METRIC_VALUE_UNAVAILABLE,
+    METRIC_VALUE_UNAVAILABLE_TO_AVAILABLE, METRIC_VALUE_UNAVAILABLE_TO_DEGRADED,
 };
+use crate::runner::probe::{DnsProbe, HttpProbe, TcpProbe, TlsProbe};
 use crate::runner::target::Target;
 use crate::runner::url::vec_to_string;
-use crate::runner::{Event, Status};
+use crate::runner::{dns, http, tcp, tls, Probe, ProbeResult, RunnerEvent, Timings, TlsOptions};
+use crate::runner::{
+    Event, Expectations, Quorum, Registry, State, Status, Strategy, TargetReport, TargetStatus,
+};
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -31,6 +48,52 @@ pub enum Error {
     },
     #[error("{status}")]
     StatusError { status: u16 },
+    #[error("{source}")]
+    IoError {
+        #[from]
+        source: std::io::Error,
+    },
+    #[error("timed out after {timeout_ms}ms")]
+    TimeoutError { timeout_ms: u64 },
+    #[error("invalid url {url}: {reason}")]
+    InvalidUrl { url: String, reason: String },
+    #[error("{source}")]
+    DnsError {
+        #[from]
+        source: hickory_resolver::error::ResolveError,
+    },
+    #[error("dns answers [{}] did not contain [{}]", .actual.join(","), .expected.join(","))]
+    DnsAnswerMismatch {
+        expected: Vec<String>,
+        actual: Vec<String>,
+    },
+    #[error("{source}")]
+    TlsError {
+        #[from]
+        source: native_tls::Error,
+    },
+    #[error("{reason}")]
+    TlsCertificateInvalid { reason: String },
+    #[error("{source}")]
+    HyperError {
+        #[from]
+        source: hyper::Error,
+    },
+    #[error("{source}")]
+    HttpError {
+        #[from]
+        source: hyper::http::Error,
+    },
+    #[error("proxy tunnel failed: {reason}")]
+    ProxyTunnelFailed { reason: String },
+    #[error("stopped after {max} redirects")]
+    TooManyRedirects { max: usize },
+    #[error("invalid user agent {user_agent}")]
+    InvalidUserAgent { user_agent: String },
+    #[error("status {status} not in {expected}")]
+    UnexpectedStatus { status: u16, expected: String },
+    #[error("assertion {assertion} failed: {reason}")]
+    AssertionFailed { assertion: String, reason: String },
 }
 
 /// Runner is a struct that runs a check on a target.
@@ -39,28 +102,93 @@ pub struct Runner {
     target: Target,
     connect_timeout_ms: u64,
     timeout_ms: u64,
-    wait_time_seconds: u64,
+    wait_time_ms: u64,
+    jitter_ms: u64,
+    start_offset_ms: u64,
     pub failure_threshold: u8,
+    pub recovery_threshold: u8,
+    pub strategy: Strategy,
+    pub quorum: Quorum,
+    pub fan_out: bool,
+    pub concurrency: usize,
     run_for_seconds: Option<u64>,
     run_for_iterations: Option<u64>,
     user_agent: String,
+    headers: HeaderMap,
+    method: Method,
+    body: Option<Bytes>,
+    expectations: Expectations,
+    pub latency_threshold_ms: Option<u64>,
+    tls: TlsOptions,
+    registry: Option<Registry>,
+    notifier: Option<Notifier>,
+    probes: HashMap<Url, Arc<dyn Probe>>,
+    events: broadcast::Sender<RunnerEvent>,
 
     pub metrics: Metrics,
 }
 
 impl Runner {
+    /// Subscribe to the events of the Runner, each check of a url and each change of the target
+    /// status. Events published before subscribing are not received.
+    ///
+    /// returns: broadcast::Receiver<RunnerEvent>
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use netcheck::runner::{RunnerBuilder, RunnerEvent};
+    /// # async fn example() {
+    /// let runner = RunnerBuilder::new().build();
+    /// let mut events = runner.subscribe();
+    /// tokio::spawn(async move { runner.run().await });
+    ///
+    /// while let Ok(event) = events.recv().await {
+    ///     if let RunnerEvent::TargetChanged { target, event, .. } = event {
+    ///         println!("{} changed: {:?}", target, event);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn subscribe(&self) -> broadcast::Receiver<RunnerEvent> {
+        self.events.subscribe()
+    }
+
     /// Run the check on the target.
-    #[tracing::instrument(level = "info")]
     pub async fn run(&self) -> Result<(), Error> {
-        let client = self.get_client()?;
-        let runner = self.clone();
-        let urls = self.target.urls.clone();
-        let wait = self.wait_time_seconds.clone();
-        let mut status = Status::new(self.failure_threshold);
-
-        let forever = task::spawn(async move {
-            runner.tick(urls, client, wait, &mut status).await;
-        });
+        self.run_until(CancellationToken::new()).await
+    }
+
+    /// Run the check on the target until the token is cancelled. A check in flight when the token
+    /// is cancelled is finished and reported before returning.
+    ///
+    /// # Arguments
+    ///
+    /// * `shutdown`: The token cancelled to stop the Runner.
+    ///
+    /// returns: Result<(), Error>
+    #[tracing::instrument(level = "info")]
+    pub async fn run_until(
+        &self,
+        shutdown: CancellationToken,
+    ) -> Result<(), Error> {
+        let probes = self
+            .target
+            .urls
+            .iter()
+            .map(|url| self.get_probe(url))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut status = TargetStatus::new(
+            self.quorum,
+            self.target
+                .urls
+                .iter()
+                .map(|_| {
+                    Status::new(self.failure_threshold, self.recovery_threshold)
+                        .with_strategy(self.strategy)
+                })
+                .collect(),
+        );
 
         self.metrics.status.observe(
             1,
@@ -73,30 +201,57 @@ impl Runner {
             ],
         );
 
-        forever.await?;
+        // Spread out the first check so runners started together do not tick in lockstep.
+        if self.start_offset_ms > 0 {
+            let offset = rand::thread_rng().gen_range(0..=self.start_offset_ms);
+            debug!(
+                runner_target = self.target.name,
+                offset_ms = offset,
+                "delaying first check"
+            );
+            tokio::select! {
+                _ = time::sleep(Duration::from_millis(offset)) => {}
+                _ = shutdown.cancelled() => return Ok(()),
+            }
+        }
+
+        // Ticking on the calling task means aborting it also stops the checks.
+        self.tick(
+            self.target.urls.clone(),
+            probes,
+            self.wait_time_ms,
+            &mut status,
+            &shutdown,
+        )
+        .await;
+        debug!(runner_target = self.target.name, "runner stopped");
 
         Ok(())
     }
 
-    /// Run the check on the target.
+    /// Run the check on the target, checking one url per interval or every url per interval when
+    /// fanning out.
     ///
     /// # Arguments
     ///
     /// * `urls`: The urls to check.
-    /// * `client`: The client to use for the check.
-    /// * `wait`: The time to wait between checks.
-    /// * `status`: The global status lock.
+    /// * `probes`: The probe checking each url.
+    /// * `wait_ms`: The time to wait between checks in milliseconds.
+    /// * `status`: The status of the target and each of its urls.
+    /// * `shutdown`: The token cancelled to stop ticking.
     ///
     /// returns: ()
     #[tracing::instrument(level = "debug")]
     async fn tick(
         &self,
         urls: Vec<Url>,
-        client: Client,
-        wait: u64,
-        status: &mut Status,
+        probes: Vec<Arc<dyn Probe>>,
+        wait_ms: u64,
+        status: &mut TargetStatus,
+        shutdown: &CancellationToken,
     ) {
-        let mut interval = time::interval(Duration::from_secs(wait));
+        let mut interval = time::interval(Duration::from_millis(wait_ms.max(1)));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
         let mut idx = 0;
         let target_name = self.target.name.clone();
         let started = Instant::now();
@@ -104,10 +259,27 @@ impl Runner {
         let mut available_count = 0;
 
         loop {
-            if self.should_stop(started, iterations) {
+            if self.should_stop(started, iterations) || shutdown.is_cancelled() {
                 break;
             }
 
+            if self.fan_out {
+                let available_count = self.check_urls(&urls, &probes, status, &target_name).await;
+                info!(
+                    runner_target = target_name,
+                    "target tick complete, {}/{} available",
+                    available_count,
+                    urls.len()
+                );
+                self.handle_target_event(status.evaluate(), status, &target_name);
+                self.update_target_metrics(status, &target_name);
+                self.report(status, shutdown);
+
+                iterations += 1;
+                self.wait(&mut interval, shutdown).await;
+                continue;
+            }
+
             if idx >= urls.len() {
                 info!(
                     runner_target = target_name,
@@ -121,59 +293,172 @@ impl Runner {
 
             let url = &urls[idx];
             let is_available = self
-                .check_url(url.clone(), client.clone(), status, target_name.clone())
+                .check_url(
+                    url.clone(),
+                    probes[idx].clone(),
+                    &mut status.urls[idx],
+                    target_name.clone(),
+                )
                 .await;
+            self.handle_target_event(status.evaluate(), status, &target_name);
+            self.update_target_metrics(status, &target_name);
+            self.report(status, shutdown);
 
             available_count += if is_available { 1 } else { 0 };
             iterations += 1;
             idx += 1;
+            self.wait(&mut interval, shutdown).await;
+        }
+    }
+
+    /// Publish the status of the target to the registry, if there is one and the runner was not
+    /// stopped while checking.
+    fn report(
+        &self,
+        status: &TargetStatus,
+        shutdown: &CancellationToken,
+    ) {
+        if let Some(registry) = &self.registry {
+            registry.update_unless_cancelled(TargetReport::new(&self.target, status), shutdown);
+        }
+    }
+
+    /// Wait for the next interval, plus a random jitter of up to `jitter_ms`, or until the
+    /// shutdown token is cancelled.
+    async fn wait(
+        &self,
+        interval: &mut Interval,
+        shutdown: &CancellationToken,
+    ) {
+        let wait = async {
             interval.tick().await;
+            if self.jitter_ms > 0 {
+                let jitter = rand::thread_rng().gen_range(0..=self.jitter_ms);
+                time::sleep(Duration::from_millis(jitter)).await;
+            }
+        };
+
+        tokio::select! {
+            _ = wait => {}
+            _ = shutdown.cancelled() => {}
         }
     }
 
-    /// Get the client to use for the check.
-    fn get_client(&self) -> reqwest::Result<Client> {
-        Client::builder()
-            .user_agent(self.user_agent.as_str())
-            .timeout(Duration::from_millis(self.timeout_ms))
-            .connect_timeout(Duration::from_millis(self.connect_timeout_ms))
-            .build()
+    /// Check every url concurrently, up to the concurrency limit, as a single round.
+    ///
+    /// # Arguments
+    ///
+    /// * `urls`: The urls to check.
+    /// * `probes`: The probe checking each url.
+    /// * `status`: The status of the target and each of its urls.
+    /// * `target`: The target name.
+    ///
+    /// returns: usize, the number of urls which were available.
+    async fn check_urls(
+        &self,
+        urls: &[Url],
+        probes: &[Arc<dyn Probe>],
+        status: &mut TargetStatus,
+        target: &str,
+    ) -> usize {
+        // Collecting the checks up front keeps the runner future Send so it can be spawned.
+        let checks: Vec<_> = urls
+            .iter()
+            .zip(probes.iter())
+            .zip(status.urls.iter_mut())
+            .map(|((url, probe), status)| {
+                self.check_url(url.clone(), probe.clone(), status, target.to_string())
+            })
+            .collect();
+
+        stream::iter(checks)
+            .buffer_unordered(self.concurrency.max(1))
+            .collect::<Vec<bool>>()
+            .await
+            .into_iter()
+            .filter(|is_available| *is_available)
+            .count()
+    }
+
+    /// Get the client requesting the url.
+    fn get_client(
+        &self,
+        url: &Url,
+    ) -> Result<http::Client, Error> {
+        let mut headers = self.headers.clone();
+        headers.insert(
+            USER_AGENT,
+            HeaderValue::from_str(&self.user_agent).map_err(|_| Error::InvalidUserAgent {
+                user_agent: self.user_agent.clone(),
+            })?,
+        );
+
+        http::Client::new(
+            url,
+            headers,
+            self.connect_timeout_ms,
+            self.timeout_ms,
+            &self.tls,
+        )
+    }
+
+    /// Get the probe checking the url, a registered probe or one picked from its scheme.
+    fn get_probe(
+        &self,
+        url: &Url,
+    ) -> Result<Arc<dyn Probe>, Error> {
+        if let Some(probe) = self.probes.get(url) {
+            return Ok(probe.clone());
+        }
+
+        Ok(match url.scheme() {
+            tcp::SCHEME_TCP => Arc::new(TcpProbe {
+                url: url.clone(),
+                connect_timeout_ms: self.connect_timeout_ms,
+            }),
+            dns::SCHEME_DNS => Arc::new(DnsProbe {
+                url: url.clone(),
+                timeout_ms: self.timeout_ms,
+            }),
+            tls::SCHEME_TLS => Arc::new(TlsProbe {
+                url: url.clone(),
+                tls: self.tls.clone(),
+                connect_timeout_ms: self.connect_timeout_ms,
+                timeout_ms: self.timeout_ms,
+                target: self.target.name.clone(),
+                metrics: self.metrics.tls.clone(),
+            }),
+            _ => Arc::new(HttpProbe {
+                client: self.get_client(url)?,
+                method: self.method.clone(),
+                body: self.body.clone(),
+                expectations: self.expectations.clone(),
+            }),
+        })
     }
 
     #[tracing::instrument(level = "debug")]
     async fn check_url(
         &self,
         url: Url,
-        client: Client,
+        probe: Arc<dyn Probe>,
         status: &mut Status,
         target: String,
     ) -> bool {
-        let start = Instant::now();
-        let resp = client.get(url.as_str()).send().await;
-
-        return match resp {
-            Ok(resp) => {
-                if resp.status().is_server_error() || resp.status().is_client_error() {
-                    let status_error = Error::StatusError {
-                        status: resp.status().as_u16(),
-                    };
-                    self.handle_response_error(status_error, target, url, start, status)
-                        .await;
-
-                    return false;
-                }
-
-                self.handle_response_ok(target, url, start, status).await;
-
-                true
-            }
-            Err(err) => {
-                self.handle_response_error(Error::from(err), target, url, start, status)
-                    .await;
+        let result = probe.probe().await;
+        if result.success {
+            let is_slow = result.degraded
+                || self
+                    .latency_threshold_ms
+                    .is_some_and(|threshold| result.latency > Duration::from_millis(threshold));
+            self.handle_response_ok(&result, is_slow, target, url, status)
+                .await;
+        } else {
+            self.handle_response_error(&result, target, url, status)
+                .await;
+        }
 
-                false
-            }
-        };
+        result.success
     }
 
     fn should_stop(
@@ -198,34 +483,38 @@ impl Runner {
 
     async fn handle_response_error(
         &self,
-        err: Error,
+        result: &ProbeResult,
         target: String,
         url: Url,
-        start: Instant,
         status: &mut Status,
     ) {
         debug!(
             runner_target = target,
             url = url.to_string(),
-            err = err.to_string(),
-            resp_ns = start.elapsed().as_nanos(),
+            err = result.error,
+            resp_ns = result.latency.as_nanos(),
             "tick failure"
         );
-        self.update_request_metrics(false, &start, target.clone(), url.clone());
+        self.update_request_metrics(result, target.clone(), url.clone());
+        status.last_checked = Some(chrono::Utc::now());
+        status.last_error = result.error.clone();
+        status.last_latency_ms = Some(result.latency.as_secs_f64() * 1000.0);
 
-        match status.handle_unavailable() {
-            Event::AvailableToUnavailable => {
-                self.metrics.events.add(
-                    1,
-                    &[
-                        KeyValue::new(METRIC_LABEL_STATUS, METRIC_VALUE_AVAILABLE_TO_UNAVAILABLE),
-                        KeyValue::new(METRIC_LABEL_TARGET_NAME, target.clone()),
-                    ],
-                );
+        let event = status.handle_unavailable();
+        self.update_url_metrics(status, &target, &url);
+        self.publish_url_checked(result, event, status, &target, &url);
+
+        match event {
+            Event::AvailableToUnavailable | Event::DegradedToUnavailable => {
                 info!(
                     runner_target = target,
                     url = url.to_string(),
-                    "available to unavailable"
+                    "url {} to unavailable",
+                    if event == Event::DegradedToUnavailable {
+                        METRIC_VALUE_DEGRADED
+                    } else {
+                        METRIC_VALUE_AVAILABLE
+                    }
                 );
             }
             _ => {
@@ -245,55 +534,148 @@ impl Runner {
 
     async fn handle_response_ok(
         &self,
+        result: &ProbeResult,
+        is_slow: bool,
         target: String,
         url: Url,
-        start: Instant,
         status: &mut Status,
     ) {
         debug!(
             runner_target = target,
             url = url.to_string(),
-            resp_ns = start.elapsed().as_nanos(),
+            resp_ns = result.latency.as_nanos(),
+            slow = is_slow,
             "tick success"
         );
-        self.update_request_metrics(true, &start, target.clone(), url.clone());
+        self.update_request_metrics(result, target.clone(), url.clone());
+        status.last_checked = Some(chrono::Utc::now());
+        status.last_error = None;
+        status.last_latency_ms = Some(result.latency.as_secs_f64() * 1000.0);
 
-        match status.handle_available() {
-            Event::UnavailableToAvailable(diff) => {
-                self.metrics.events.add(
-                    1,
-                    &[
-                        KeyValue::new(METRIC_LABEL_STATUS, METRIC_VALUE_UNAVAILABLE_TO_AVAILABLE),
-                        KeyValue::new(METRIC_LABEL_TARGET_NAME, target.clone()),
-                    ],
-                );
+        let event = if is_slow {
+            status.handle_slow()
+        } else {
+            status.handle_available()
+        };
+        self.update_url_metrics(status, &target, &url);
+        self.publish_url_checked(result, event, status, &target, &url);
+
+        match event {
+            Event::UnavailableToAvailable(diff)
+            | Event::UnavailableToDegraded(diff)
+            | Event::DegradedToAvailable(diff) => {
                 info!(
                     runner_target = target,
                     url = url.to_string(),
                     diff = diff.num_seconds(),
-                    "unavailable to available"
+                    "url {}",
+                    event_value(&event).unwrap_or_default().replace('_', " ")
+                );
+            }
+            Event::AvailableToDegraded => {
+                info!(
+                    runner_target = target,
+                    url = url.to_string(),
+                    resp_ms = result.latency.as_millis() as u64,
+                    "url available to degraded"
                 );
             }
             _ => {}
         }
     }
 
+    /// Record and log a change of the target status.
+    ///
+    /// # Arguments
+    ///
+    /// * `event`: The event returned when evaluating the target status.
+    /// * `status`: The target status.
+    /// * `target`: The target name.
+    fn handle_target_event(
+        &self,
+        event: Event,
+        status: &TargetStatus,
+        target: &str,
+    ) {
+        if let Some(notifier) = &self.notifier {
+            if let Some(notification) = Notification::new(&self.target, status, &event) {
+                notifier.notify(notification);
+            }
+        }
+
+        if event != Event::NoChange {
+            // Sending only fails when nobody is subscribed.
+            let _ = self.events.send(RunnerEvent::TargetChanged {
+                target: target.to_string(),
+                event,
+                available_urls: status.available(),
+                total_urls: status.urls.len(),
+                timestamp: chrono::Utc::now(),
+            });
+        }
+
+        let Some(value) = event_value(&event) else {
+            return;
+        };
+        self.metrics.events.add(
+            1,
+            &[
+                KeyValue::new(METRIC_LABEL_STATUS, value),
+                KeyValue::new(METRIC_LABEL_TARGET_NAME, target.to_string()),
+            ],
+        );
+        let diff = match event {
+            Event::UnavailableToAvailable(diff)
+            | Event::DegradedToAvailable(diff)
+            | Event::UnavailableToDegraded(diff) => Some(diff.num_seconds()),
+            _ => None,
+        };
+        info!(
+            runner_target = target,
+            quorum = status.quorum.to_string(),
+            diff = diff,
+            "{}, {}/{} urls available, {} within the latency threshold",
+            value.replace('_', " "),
+            status.available(),
+            status.urls.len(),
+            status.healthy()
+        );
+    }
+
+    /// Publish the result of a check to the subscribers.
+    fn publish_url_checked(
+        &self,
+        result: &ProbeResult,
+        event: Event,
+        status: &Status,
+        target: &str,
+        url: &Url,
+    ) {
+        // Sending only fails when nobody is subscribed.
+        let _ = self.events.send(RunnerEvent::UrlChecked {
+            target: target.to_string(),
+            url: url.clone(),
+            result: result.clone(),
+            event,
+            is_unavailable: status.is_unavailable,
+            timestamp: chrono::Utc::now(),
+        });
+    }
+
     /// Update the request metrics.
     ///
     /// # Arguments
     ///
-    /// * `success`: If the request was successful.
-    /// * `started`: When the request started.
+    /// * `result`: The result of the check.
     /// * `target`: The target name.
     /// * `url`:  The target url.
     fn update_request_metrics(
         &self,
-        success: bool,
-        started: &Instant,
+        result: &ProbeResult,
         target: String,
         url: Url,
     ) {
-        let status = if success {
+        let status = if result.success {
             METRIC_VALUE_AVAILABLE
         } else {
             METRIC_VALUE_UNAVAILABLE
@@ -309,7 +691,7 @@ impl Runner {
         );
 
         self.metrics.requests_response_time_ns.record(
-            started.elapsed().as_nanos() as f64,
+            result.latency.as_nanos() as f64,
             &[
                 KeyValue::new(METRIC_LABEL_STATUS, status),
                 KeyValue::new(METRIC_LABEL_TARGET_NAME, target.clone()),
@@ -317,23 +699,123 @@ impl Runner {
             ],
         );
 
-        self.metrics.target_status.observe(
-            if success { 1 } else { 0 },
-            &[
-                KeyValue::new(METRIC_LABEL_TARGET_NAME, target.clone()),
-                KeyValue::new(METRIC_LABEL_STATUS, METRIC_VALUE_AVAILABLE),
-                KeyValue::new(METRIC_LABEL_URLS, vec_to_string(self.target.urls.clone())),
-            ],
-        );
+        if let Some(timings) = &result.timings {
+            self.update_phase_metrics(timings, status, &target, &url);
+        }
+    }
 
-        self.metrics.target_status.observe(
-            if success { 0 } else { 1 },
-            &[
-                KeyValue::new(METRIC_LABEL_TARGET_NAME, target.clone()),
-                KeyValue::new(METRIC_LABEL_STATUS, METRIC_VALUE_UNAVAILABLE),
-                KeyValue::new(METRIC_LABEL_URLS, vec_to_string(self.target.urls.clone())),
-            ],
+    /// Record how long each phase of the request took, skipping the phases it did not reach.
+    ///
+    /// # Arguments
+    ///
+    /// * `timings`: The timing of each phase.
+    /// * `status`: The status label of the request.
+    /// * `target`: The target name.
+    /// * `url`:  The target url.
+    fn update_phase_metrics(
+        &self,
+        timings: &Timings,
+        status: &'static str,
+        target: &str,
+        url: &Url,
+    ) {
+        debug!(
+            runner_target = target,
+            url = url.to_string(),
+            dns_ns = timings.dns.map(|phase| phase.as_nanos() as u64),
+            connect_ns = timings.connect.map(|phase| phase.as_nanos() as u64),
+            tls_ns = timings.tls.map(|phase| phase.as_nanos() as u64),
+            ttfb_ns = timings.ttfb.map(|phase| phase.as_nanos() as u64),
+            body_ns = timings.body.map(|phase| phase.as_nanos() as u64),
+            "request phases"
         );
+
+        let labels = [
+            KeyValue::new(METRIC_LABEL_STATUS, status),
+            KeyValue::new(METRIC_LABEL_TARGET_NAME, target.to_string()),
+            KeyValue::new(METRIC_LABEL_URL, url.to_string()),
+        ];
+        let phases = &self.metrics.phases;
+        for (histogram, phase) in [
+            (&phases.dns_time_ns, timings.dns),
+            (&phases.connect_time_ns, timings.connect),
+            (&phases.tls_time_ns, timings.tls),
+            (&phases.time_to_first_byte_ns, timings.ttfb),
+            (&phases.body_time_ns, timings.body),
+        ] {
+            if let Some(phase) = phase {
+                histogram.record(phase.as_nanos() as f64, &labels);
+            }
+        }
+    }
+
+    /// Update the status metrics of a url.
+    ///
+    /// # Arguments
+    ///
+    /// * `status`: The status of the url.
+    /// * `target`: The target name.
+    /// * `url`:  The target url.
+    fn update_url_metrics(
+        &self,
+        status: &Status,
+        target: &str,
+        url: &Url,
+    ) {
+        for (value, state) in [
+            (METRIC_VALUE_AVAILABLE, State::Available),
+            (METRIC_VALUE_DEGRADED, State::Degraded),
+            (METRIC_VALUE_UNAVAILABLE, State::Unavailable),
+        ] {
+            self.metrics.url_status.observe(
+                if status.state() == state { 1 } else { 0 },
+                &[
+                    KeyValue::new(METRIC_LABEL_TARGET_NAME, target.to_string()),
+                    KeyValue::new(METRIC_LABEL_STATUS, value),
+                    KeyValue::new(METRIC_LABEL_URL, url.to_string()),
+                ],
+            );
+        }
+    }
+
+    /// Update the status metrics of the target, derived from its urls using the quorum.
+    ///
+    /// # Arguments
+    ///
+    /// * `status`: The target status.
+    /// * `target`: The target name.
+    fn update_target_metrics(
+        &self,
+        status: &TargetStatus,
+        target: &str,
+    ) {
+        for (value, state) in [
+            (METRIC_VALUE_AVAILABLE, State::Available),
+            (METRIC_VALUE_DEGRADED, State::Degraded),
+            (METRIC_VALUE_UNAVAILABLE, State::Unavailable),
+        ] {
+            self.metrics.target_status.observe(
+                if status.state() == state { 1 } else { 0 },
+                &[
+                    KeyValue::new(METRIC_LABEL_TARGET_NAME, target.to_string()),
+                    KeyValue::new(METRIC_LABEL_STATUS, value),
+                    KeyValue::new(METRIC_LABEL_URLS, vec_to_string(self.target.urls.clone())),
+                ],
+            );
+        }
+    }
+}
+
+/// The metric value of an event, or None if the status did not change.
+fn event_value(event: &Event) -> Option<&'static str> {
+    match event {
+        Event::AvailableToUnavailable => Some(METRIC_VALUE_AVAILABLE_TO_UNAVAILABLE),
+        Event::UnavailableToAvailable(_) => Some(METRIC_VALUE_UNAVAILABLE_TO_AVAILABLE),
+        Event::AvailableToDegraded => Some(METRIC_VALUE_AVAILABLE_TO_DEGRADED),
+        Event::DegradedToAvailable(_) => Some(METRIC_VALUE_DEGRADED_TO_AVAILABLE),
+        Event::DegradedToUnavailable => Some(METRIC_VALUE_DEGRADED_TO_UNAVAILABLE),
+        Event::UnavailableToDegraded(_) => Some(METRIC_VALUE_UNAVAILABLE_TO_DEGRADED),
+        Event::NoChange => None,
     }
 }
 
@@ -356,11 +838,28 @@ pub struct RunnerBuilder {
     target: Target,
     connect_timeout_ms: u64,
     timeout_ms: u64,
-    wait_time_seconds: u64,
+    wait_time_ms: u64,
+    jitter_ms: u64,
+    start_offset_ms: u64,
     failure_threshold: u8,
+    recovery_threshold: u8,
+    strategy: Strategy,
+    quorum: Quorum,
+    fan_out: bool,
+    concurrency: usize,
     run_for_seconds: Option<u64>,
     run_for_iterations: Option<u64>,
     user_agent: Option<String>,
+    headers: HeaderMap,
+    method: Method,
+    body: Option<Bytes>,
+    expectations: Expectations,
+    latency_threshold_ms: Option<u64>,
+    tls: TlsOptions,
+    registry: Option<Registry>,
+    notifier: Option<Notifier>,
+    probes: Vec<(Url, Arc<dyn Probe>)>,
+    events: Option<broadcast::Sender<RunnerEvent>>,
 }
 
 impl RunnerBuilder {
@@ -410,7 +909,34 @@ impl RunnerBuilder {
         mut self,
         wait_time_seconds: u64,
     ) -> RunnerBuilder {
-        self.wait_time_seconds = wait_time_seconds;
+        self.wait_time_ms = wait_time_seconds * 1000;
+        self
+    }
+
+    /// Set the wait_time_ms of the RunnerBuilder, for intervals below a second.
+    pub fn wait_time_ms(
+        mut self,
+        wait_time_ms: u64,
+    ) -> RunnerBuilder {
+        self.wait_time_ms = wait_time_ms;
+        self
+    }
+
+    /// Set the maximum random delay added to each interval of the RunnerBuilder.
+    pub fn jitter_ms(
+        mut self,
+        jitter_ms: u64,
+    ) -> RunnerBuilder {
+        self.jitter_ms = jitter_ms;
+        self
+    }
+
+    /// Set the maximum random delay before the first check of the RunnerBuilder.
+    pub fn start_offset_ms(
+        mut self,
+        start_offset_ms: u64,
+    ) -> RunnerBuilder {
+        self.start_offset_ms = start_offset_ms;
         self
     }
 
@@ -423,6 +949,52 @@ impl RunnerBuilder {
         self
     }
 
+    /// Set the recovery_threshold of the RunnerBuilder.
+    pub fn recovery_threshold(
+        mut self,
+        recovery_threshold: u8,
+    ) -> RunnerBuilder {
+        self.recovery_threshold = recovery_threshold;
+        self
+    }
+
+    /// Set the strategy used to evaluate the checks of the RunnerBuilder.
+    pub fn strategy(
+        mut self,
+        strategy: Strategy,
+    ) -> RunnerBuilder {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Set the quorum of urls which need to be available for the target to be available.
+    pub fn quorum(
+        mut self,
+        quorum: Quorum,
+    ) -> RunnerBuilder {
+        self.quorum = quorum;
+        self
+    }
+
+    /// Set if every url of the target is checked each interval, instead of one url per interval.
+    /// When fanning out, `run_for_iterations` counts rounds rather than single checks.
+    pub fn fan_out(
+        mut self,
+        fan_out: bool,
+    ) -> RunnerBuilder {
+        self.fan_out = fan_out;
+        self
+    }
+
+    /// Set the maximum number of urls checked at once when fanning out.
+    pub fn concurrency(
+        mut self,
+        concurrency: usize,
+    ) -> RunnerBuilder {
+        self.concurrency = concurrency;
+        self
+    }
+
     /// Set the run_for_seconds of the RunnerBuilder.
     pub fn run_for_seconds(
         mut self,
@@ -449,54 +1021,200 @@ impl RunnerBuilder {
         self
     }
 
-    /// Build the Runner.
-    ///
-    /// returns: Runner
-    ///
-    /// # Example
-    ///
-    /// ```
-    /// # use netcheck::runner::{RunnerBuilder, Target};
-    /// # use reqwest::Url;
-    /// let runner = RunnerBuilder::new()
-    ///     .target(Target::new("external".to_string(), vec![Url::parse("https://example.com").unwrap()]))
-    ///     .connect_timeout_ms(1000)
-    ///     .timeout_ms(1000)
-    ///     .wait_time_seconds(1)
-    ///     .build();
-    /// ```
-    pub fn build(self) -> Runner {
-        Runner {
-            target: self.target,
-            connect_timeout_ms: self.connect_timeout_ms,
-            timeout_ms: self.timeout_ms,
-            wait_time_seconds: self.wait_time_seconds,
-            failure_threshold: self.failure_threshold,
-            run_for_seconds: self.run_for_seconds,
-            run_for_iterations: self.run_for_iterations,
-            user_agent: self.user_agent.unwrap_or_else(get_user_agent),
-            metrics: Metrics::default(),
-        }
+    /// Set the headers sent with every http request of the RunnerBuilder.
+    pub fn headers(
+        mut self,
+        headers: HeaderMap,
+    ) -> RunnerBuilder {
+        self.headers = headers;
+        self
     }
-}
 
-impl Default for RunnerBuilder {
-    fn default() -> Self {
-        RunnerBuilder {
-            target: Target {
-                name: "external".to_string(),
-                urls: vec![
-                    Url::parse("https://1.1.1.1").expect("default url not valid"),
-                    Url::parse("https://dns.google").expect("default url not valid"),
-                ],
-            },
+    /// Set the method of the http requests of the RunnerBuilder, defaults to GET.
+    pub fn method(
+        mut self,
+        method: Method,
+    ) -> RunnerBuilder {
+        self.method = method;
+        self
+    }
+
+    /// Set the body sent with every http request of the RunnerBuilder.
+    pub fn body(
+        mut self,
+        body: impl Into<Bytes>,
+    ) -> RunnerBuilder {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Set what the http responses of the RunnerBuilder have to match, by default any status below
+    /// 400.
+    pub fn expectations(
+        mut self,
+        expectations: Expectations,
+    ) -> RunnerBuilder {
+        self.expectations = expectations;
+        self
+    }
+
+    /// Set the latency above which successful checks count as slow, making the urls and the
+    /// target degraded rather than available. The slow and fast streaks reuse the
+    /// `failure_threshold` and `recovery_threshold` of the RunnerBuilder.
+    pub fn latency_threshold_ms(
+        mut self,
+        latency_threshold_ms: u64,
+    ) -> RunnerBuilder {
+        self.latency_threshold_ms = Some(latency_threshold_ms);
+        self
+    }
+
+    /// Set the TLS options of the https requests of the RunnerBuilder, e.g. a private CA or a
+    /// client certificate.
+    pub fn tls(
+        mut self,
+        tls: TlsOptions,
+    ) -> RunnerBuilder {
+        self.tls = tls;
+        self
+    }
+
+    /// Set the registry the Runner publishes the status of the target to.
+    pub fn registry(
+        mut self,
+        registry: Registry,
+    ) -> RunnerBuilder {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Set the notifier the Runner sends status changes of the target to.
+    pub fn notifier(
+        mut self,
+        notifier: Notifier,
+    ) -> RunnerBuilder {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// Check the url with a custom probe instead of the one picked from its scheme, the url is
+    /// added to the target if it is not one of its urls.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The url the probe checks, used to track its status.
+    /// * `probe`: The probe to check the url with.
+    ///
+    /// returns: RunnerBuilder
+    pub fn probe(
+        mut self,
+        url: Url,
+        probe: impl Probe + 'static,
+    ) -> RunnerBuilder {
+        self.probes.push((url, Arc::new(probe)));
+        self
+    }
+
+    /// Set the channel the Runner publishes its events to, so several runners can share one.
+    /// By default each Runner has its own channel, see `Runner::subscribe`.
+    pub fn events(
+        mut self,
+        events: broadcast::Sender<RunnerEvent>,
+    ) -> RunnerBuilder {
+        self.events = Some(events);
+        self
+    }
+
+    /// Build the Runner.
+    ///
+    /// returns: Runner
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use netcheck::runner::{RunnerBuilder, Target};
+    /// # use reqwest::Url;
+    /// let runner = RunnerBuilder::new()
+    ///     .target(Target::new("external".to_string(), vec![Url::parse("https://example.com").unwrap()]))
+    ///     .connect_timeout_ms(1000)
+    ///     .timeout_ms(1000)
+    ///     .wait_time_seconds(1)
+    ///     .build();
+    /// ```
+    pub fn build(mut self) -> Runner {
+        for (url, _) in &self.probes {
+            if !self.target.urls.contains(url) {
+                self.target.urls.push(url.clone());
+            }
+        }
+
+        Runner {
+            target: self.target,
+            connect_timeout_ms: self.connect_timeout_ms,
+            timeout_ms: self.timeout_ms,
+            wait_time_ms: self.wait_time_ms,
+            jitter_ms: self.jitter_ms,
+            start_offset_ms: self.start_offset_ms,
+            failure_threshold: self.failure_threshold,
+            recovery_threshold: self.recovery_threshold,
+            strategy: self.strategy,
+            quorum: self.quorum,
+            fan_out: self.fan_out,
+            concurrency: self.concurrency,
+            run_for_seconds: self.run_for_seconds,
+            run_for_iterations: self.run_for_iterations,
+            user_agent: self.user_agent.unwrap_or_else(get_user_agent),
+            headers: self.headers,
+            method: self.method,
+            body: self.body,
+            expectations: self.expectations,
+            latency_threshold_ms: self.latency_threshold_ms,
+            tls: self.tls,
+            registry: self.registry,
+            notifier: self.notifier,
+            probes: self.probes.into_iter().collect(),
+            events: self
+                .events
+                .unwrap_or_else(|| broadcast::channel(DEFAULT_EVENTS_CAPACITY).0),
+            metrics: Metrics::default(),
+        }
+    }
+}
+
+impl Default for RunnerBuilder {
+    fn default() -> Self {
+        RunnerBuilder {
+            target: Target {
+                name: "external".to_string(),
+                urls: vec![
+                    Url::parse("https://1.1.1.1").expect("default url not valid"),
+                    Url::parse("https://dns.google").expect("default url not valid"),
+                ],
+            },
             connect_timeout_ms: 1000,
             timeout_ms: 1000,
-            wait_time_seconds: 1,
+            wait_time_ms: 1000,
+            jitter_ms: 0,
+            start_offset_ms: 0,
             failure_threshold: 5,
+            recovery_threshold: 5,
+            strategy: Strategy::default(),
+            quorum: Quorum::default(),
+            fan_out: false,
+            concurrency: 10,
             run_for_seconds: None,
             run_for_iterations: None,
             user_agent: None,
+            headers: HeaderMap::new(),
+            method: Method::GET,
+            body: None,
+            expectations: Expectations::default(),
+            latency_threshold_ms: None,
+            tls: TlsOptions::default(),
+            registry: None,
+            notifier: None,
+            probes: Vec::new(),
+            events: None,
         }
     }
 }
@@ -509,10 +1227,37 @@ fn get_user_agent() -> String {
 mod tests {
     use httpmock::prelude::*;
     use pretty_assertions::assert_eq;
+    use reqwest::header::HeaderMap;
     use reqwest::Url;
+    use tokio_util::sync::CancellationToken;
 
     use crate::built_info;
-    use crate::runner::{RunnerBuilder, Status, Target};
+    use crate::notifier::{Channel, Notifier, Webhook};
+    use crate::runner::{BodyAssertion, Event, Expectations, Probe, ProbeResult, RunnerEvent};
+    use crate::runner::{
+        Quorum, Registry, Runner, RunnerBuilder, Status, Strategy, Target, TargetStatus,
+    };
+
+    async fn check_url(
+        runner: &Runner,
+        url: &str,
+        status: &mut Status,
+    ) -> bool {
+        let url = Url::parse(url).unwrap();
+        let probe = runner.get_probe(&url).unwrap();
+        runner
+            .check_url(url, probe, status, "test".to_string())
+            .await
+    }
+
+    fn probes(
+        runner: &Runner,
+        urls: &[Url],
+    ) -> Vec<std::sync::Arc<dyn Probe>> {
+        urls.iter()
+            .map(|url| runner.get_probe(url).unwrap())
+            .collect()
+    }
 
     #[test]
     fn test_runner_builder() {
@@ -524,6 +1269,7 @@ mod tests {
             .connect_timeout_ms(1000)
             .timeout_ms(1000)
             .failure_threshold(1)
+            .recovery_threshold(2)
             .wait_time_seconds(1)
             .run_for_seconds(1)
             .run_for_iterations(1)
@@ -538,7 +1284,8 @@ mod tests {
         assert_eq!(runner.connect_timeout_ms, 1000);
         assert_eq!(runner.timeout_ms, 1000);
         assert_eq!(runner.failure_threshold, 1);
-        assert_eq!(runner.wait_time_seconds, 1);
+        assert_eq!(runner.recovery_threshold, 2);
+        assert_eq!(runner.wait_time_ms, 1000);
         assert_eq!(runner.run_for_seconds, Some(1));
         assert_eq!(runner.run_for_iterations, Some(1));
         assert_eq!(runner.user_agent, "test");
@@ -550,8 +1297,15 @@ mod tests {
 
         assert_eq!(runner.connect_timeout_ms, 1000);
         assert_eq!(runner.timeout_ms, 1000);
-        assert_eq!(runner.wait_time_seconds, 1);
+        assert_eq!(runner.wait_time_ms, 1000);
+        assert_eq!(runner.jitter_ms, 0);
+        assert_eq!(runner.start_offset_ms, 0);
         assert_eq!(runner.failure_threshold, 5);
+        assert_eq!(runner.recovery_threshold, 5);
+        assert_eq!(runner.strategy, Strategy::Consecutive);
+        assert_eq!(runner.quorum, Quorum::Any);
+        assert_eq!(runner.fan_out, false);
+        assert_eq!(runner.concurrency, 10);
         assert_eq!(runner.run_for_seconds, None);
         assert_eq!(runner.run_for_iterations, None);
         assert_eq!(
@@ -594,7 +1348,20 @@ mod tests {
     fn test_runner_builder_wait_time_seconds() {
         let runner = RunnerBuilder::new().wait_time_seconds(1).build();
 
-        assert_eq!(runner.wait_time_seconds, 1);
+        assert_eq!(runner.wait_time_ms, 1000);
+    }
+
+    #[test]
+    fn test_runner_builder_wait_time_ms() {
+        let runner = RunnerBuilder::new()
+            .wait_time_ms(250)
+            .jitter_ms(50)
+            .start_offset_ms(100)
+            .build();
+
+        assert_eq!(runner.wait_time_ms, 250);
+        assert_eq!(runner.jitter_ms, 50);
+        assert_eq!(runner.start_offset_ms, 100);
     }
 
     #[test]
@@ -604,6 +1371,40 @@ mod tests {
         assert_eq!(runner.failure_threshold, 1);
     }
 
+    #[test]
+    fn test_runner_builder_recovery_threshold() {
+        let runner = RunnerBuilder::new().recovery_threshold(10).build();
+
+        assert_eq!(runner.recovery_threshold, 10);
+    }
+
+    #[test]
+    fn test_runner_builder_strategy() {
+        let strategy = Strategy::Window {
+            checks: Some(20),
+            seconds: None,
+            failure_percent: 30.0,
+        };
+        let runner = RunnerBuilder::new().strategy(strategy).build();
+
+        assert_eq!(runner.strategy, strategy);
+    }
+
+    #[test]
+    fn test_runner_builder_quorum() {
+        let runner = RunnerBuilder::new().quorum(Quorum::Count(2)).build();
+
+        assert_eq!(runner.quorum, Quorum::Count(2));
+    }
+
+    #[test]
+    fn test_runner_builder_fan_out() {
+        let runner = RunnerBuilder::new().fan_out(true).concurrency(2).build();
+
+        assert_eq!(runner.fan_out, true);
+        assert_eq!(runner.concurrency, 2);
+    }
+
     #[test]
     fn test_runner_builder_run_for_seconds() {
         let runner = RunnerBuilder::new().run_for_seconds(1).build();
@@ -625,6 +1426,108 @@ mod tests {
         assert_eq!(runner.user_agent, "test");
     }
 
+    #[test]
+    fn test_runner_builder_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", "secret".parse().unwrap());
+        let runner = RunnerBuilder::new().headers(headers.clone()).build();
+
+        assert_eq!(runner.headers, headers);
+    }
+
+    #[tokio::test]
+    async fn test_runner_check_url_headers() {
+        let server = MockServer::start();
+        let url = server.url("/");
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", "secret".parse().unwrap());
+        let runner = RunnerBuilder::new()
+            .target(Target::new(
+                "external".to_string(),
+                vec![Url::parse(&url).unwrap()],
+            ))
+            .headers(headers)
+            .build();
+
+        let mock = server.mock(|when, then| {
+            when.method(GET).path("/").header("x-api-key", "secret");
+            then.status(200);
+        });
+
+        let status = &mut Status::new(5, 5);
+        check_url(&runner, &url, status).await;
+
+        assert_eq!(status.available_counted, 1);
+
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_runner_check_url_method_body() {
+        let server = MockServer::start();
+        let url = server.url("/graphql");
+        let runner = RunnerBuilder::new()
+            .target(Target::new(
+                "api".to_string(),
+                vec![Url::parse(&url).unwrap()],
+            ))
+            .method(reqwest::Method::POST)
+            .body(r#"{"query": "{ health }"}"#)
+            .build();
+
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/graphql")
+                .body(r#"{"query": "{ health }"}"#);
+            then.status(200);
+        });
+
+        let status = &mut Status::new(5, 5);
+        check_url(&runner, &url, status).await;
+
+        assert_eq!(status.available_counted, 1);
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_runner_check_url_expectations() {
+        let server = MockServer::start();
+        let url = server.url("/health");
+        let runner = RunnerBuilder::new()
+            .target(Target::new(
+                "api".to_string(),
+                vec![Url::parse(&url).unwrap()],
+            ))
+            .expectations(Expectations {
+                status: vec!["401".parse().unwrap()],
+                body: vec![BodyAssertion::Contains("login".to_string())],
+            })
+            .build();
+
+        let mut mock = server.mock(|when, then| {
+            when.path("/health");
+            then.status(401).body("login required");
+        });
+
+        let status = &mut Status::new(5, 5);
+        check_url(&runner, &url, status).await;
+        assert_eq!(status.available_counted, 1);
+
+        mock.delete();
+        server.mock(|when, then| {
+            when.path("/health");
+            then.status(401).body("go away");
+        });
+
+        check_url(&runner, &url, status).await;
+        assert_eq!(status.unavailable_counted, 1);
+        assert_eq!(
+            status.last_error,
+            Some("assertion contains \"login\" failed: text not found in body".to_string())
+        );
+    }
+
     #[test]
     fn test_runner_get_user_agent() {
         let user_agent = super::get_user_agent();
@@ -699,15 +1602,8 @@ mod tests {
             then.status(200);
         });
 
-        let status = &mut Status::new(5);
-        runner
-            .check_url(
-                Url::parse(&url).unwrap(),
-                reqwest::Client::new(),
-                status,
-                "test".to_string(),
-            )
-            .await;
+        let status = &mut Status::new(5, 5);
+        check_url(&runner, &url, status).await;
 
         assert_eq!(status.is_unavailable, false);
         assert_eq!(status.unavailable_counted, 0);
@@ -728,15 +1624,8 @@ mod tests {
             ))
             .build();
 
-        let status = &mut Status::new(5);
-        runner
-            .check_url(
-                Url::parse(&url).unwrap(),
-                reqwest::Client::new(),
-                status,
-                "test".to_string(),
-            )
-            .await;
+        let status = &mut Status::new(5, 5);
+        check_url(&runner, &url, status).await;
 
         assert_eq!(status.is_unavailable, false);
         assert_eq!(status.unavailable_counted, 1);
@@ -760,15 +1649,8 @@ mod tests {
             then.status(500);
         });
 
-        let status = &mut Status::new(5);
-        runner
-            .check_url(
-                Url::parse(&url).unwrap(),
-                reqwest::Client::new(),
-                status,
-                "test".to_string(),
-            )
-            .await;
+        let status = &mut Status::new(5, 5);
+        check_url(&runner, &url, status).await;
 
         assert_eq!(status.is_unavailable, false);
         assert_eq!(status.unavailable_counted, 1);
@@ -794,15 +1676,8 @@ mod tests {
             then.status(400);
         });
 
-        let status = &mut Status::new(5);
-        runner
-            .check_url(
-                Url::parse(&url).unwrap(),
-                reqwest::Client::new(),
-                status,
-                "test".to_string(),
-            )
-            .await;
+        let status = &mut Status::new(5, 5);
+        check_url(&runner, &url, status).await;
 
         assert_eq!(status.is_unavailable, false);
         assert_eq!(status.unavailable_counted, 1);
@@ -828,31 +1703,10 @@ mod tests {
             then.status(500);
         });
 
-        let status = &mut Status::new(2);
-        runner
-            .check_url(
-                Url::parse(&url).unwrap(),
-                reqwest::Client::new(),
-                status,
-                "test".to_string(),
-            )
-            .await;
-        runner
-            .check_url(
-                Url::parse(&url).unwrap(),
-                reqwest::Client::new(),
-                status,
-                "test".to_string(),
-            )
-            .await;
-        runner
-            .check_url(
-                Url::parse(&url).unwrap(),
-                reqwest::Client::new(),
-                status,
-                "test".to_string(),
-            )
-            .await;
+        let status = &mut Status::new(2, 2);
+        check_url(&runner, &url, status).await;
+        check_url(&runner, &url, status).await;
+        check_url(&runner, &url, status).await;
 
         assert_eq!(status.is_unavailable, true);
         assert_eq!(status.unavailable_counted, 3);
@@ -878,33 +1732,12 @@ mod tests {
             then.status(200);
         });
 
-        let status = &mut Status::new(2);
+        let status = &mut Status::new(2, 2);
         status.is_unavailable = true;
         status.unavailable_started = chrono::Utc::now();
-        runner
-            .check_url(
-                Url::parse(&url).unwrap(),
-                reqwest::Client::new(),
-                status,
-                "test".to_string(),
-            )
-            .await;
-        runner
-            .check_url(
-                Url::parse(&url).unwrap(),
-                reqwest::Client::new(),
-                status,
-                "test".to_string(),
-            )
-            .await;
-        runner
-            .check_url(
-                Url::parse(&url).unwrap(),
-                reqwest::Client::new(),
-                status,
-                "test".to_string(),
-            )
-            .await;
+        check_url(&runner, &url, status).await;
+        check_url(&runner, &url, status).await;
+        check_url(&runner, &url, status).await;
 
         assert_eq!(status.is_unavailable, false);
         assert_eq!(status.available_counted, 3);
@@ -932,20 +1765,408 @@ mod tests {
             then.status(200).delay(std::time::Duration::from_secs(1));
         });
 
-        let client = runner.get_client().expect("failed to get client");
+        let status = &mut Status::new(2, 2);
+        check_url(&runner, &url, status).await;
+
+        assert_eq!(status.unavailable_counted, 1);
+
+        mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_runner_check_url_tcp_available() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("tcp://{}", listener.local_addr().unwrap())).unwrap();
+
+        let runner = RunnerBuilder::new()
+            .target(Target::new("database".to_string(), vec![url.clone()]))
+            .build();
+
+        let status = &mut Status::new(5, 5);
+        let is_available = check_url(&runner, url.as_str(), status).await;
+
+        assert_eq!(is_available, true);
+        assert_eq!(status.unavailable_counted, 0);
+        assert_eq!(status.available_counted, 1);
+    }
+
+    #[tokio::test]
+    async fn test_runner_check_url_tcp_unavailable() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("tcp://{}", listener.local_addr().unwrap())).unwrap();
+        drop(listener);
+
+        let runner = RunnerBuilder::new()
+            .target(Target::new("database".to_string(), vec![url.clone()]))
+            .build();
+
+        let status = &mut Status::new(5, 5);
+        let is_available = check_url(&runner, url.as_str(), status).await;
+
+        assert_eq!(is_available, false);
+        assert_eq!(status.unavailable_counted, 1);
+        assert_eq!(status.available_counted, 0);
+    }
+
+    #[tokio::test]
+    async fn test_runner_tick_quorum() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let up = Url::parse(&format!("tcp://{}", listener.local_addr().unwrap())).unwrap();
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let down = Url::parse(&format!("tcp://{}", closed.local_addr().unwrap())).unwrap();
+        drop(closed);
+
+        for (quorum, is_unavailable) in [(Quorum::Any, false), (Quorum::All, true)] {
+            let runner = RunnerBuilder::new()
+                .target(Target::new(
+                    "mirrors".to_string(),
+                    vec![up.clone(), down.clone()],
+                ))
+                .failure_threshold(1)
+                .quorum(quorum)
+                .run_for_iterations(2)
+                .build();
+
+            let status = &mut TargetStatus::new(quorum, vec![Status::new(1, 1), Status::new(1, 1)]);
+            runner
+                .tick(
+                    runner.target.urls.clone(),
+                    probes(&runner, &runner.target.urls),
+                    1,
+                    status,
+                    &CancellationToken::new(),
+                )
+                .await;
+
+            assert_eq!(status.urls[0].is_unavailable, false);
+            assert_eq!(status.urls[1].is_unavailable, true);
+            assert_eq!(status.is_unavailable, is_unavailable);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_runner_tick_fan_out() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(GET).path("/");
+            then.status(200)
+                .delay(std::time::Duration::from_millis(200));
+        });
+        let urls: Vec<Url> = (0..4)
+            .map(|i| Url::parse(&server.url(format!("/?mirror={}", i))).unwrap())
+            .collect();
+
+        let runner = RunnerBuilder::new()
+            .target(Target::new("mirrors".to_string(), urls.clone()))
+            .fan_out(true)
+            .concurrency(4)
+            .run_for_iterations(1)
+            .build();
+
+        let status = &mut TargetStatus::new(
+            Quorum::All,
+            urls.iter().map(|_| Status::new(1, 1)).collect(),
+        );
+        let started = std::time::Instant::now();
+        let available = runner
+            .check_urls(&urls, &probes(&runner, &urls), status, "mirrors")
+            .await;
+
+        assert_eq!(available, 4);
+        assert!(started.elapsed() < std::time::Duration::from_millis(600));
+        assert!(status.urls.iter().all(|url| url.available_counted == 1));
+        mock.assert_hits(4);
 
-        let status = &mut Status::new(2);
         runner
-            .check_url(
-                Url::parse(&url).unwrap(),
-                client.clone(),
+            .tick(
+                urls.clone(),
+                probes(&runner, &urls),
+                1,
                 status,
-                "test".to_string(),
+                &CancellationToken::new(),
             )
             .await;
+        assert!(status.urls.iter().all(|url| url.available_counted == 2));
+        mock.assert_hits(8);
+    }
 
-        assert_eq!(status.unavailable_counted, 1);
+    #[tokio::test]
+    async fn test_runner_tick_wait_time_ms() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("tcp://{}", listener.local_addr().unwrap())).unwrap();
+
+        let runner = RunnerBuilder::new()
+            .target(Target::new("database".to_string(), vec![url.clone()]))
+            .wait_time_ms(50)
+            .jitter_ms(10)
+            .run_for_iterations(4)
+            .build();
+
+        let status = &mut TargetStatus::new(Quorum::Any, vec![Status::new(1, 1)]);
+        let started = std::time::Instant::now();
+        runner
+            .tick(
+                vec![url.clone()],
+                probes(&runner, &[url]),
+                runner.wait_time_ms,
+                status,
+                &CancellationToken::new(),
+            )
+            .await;
+
+        assert_eq!(status.urls[0].available_counted, 4);
+        assert!(started.elapsed() >= std::time::Duration::from_millis(150));
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+    }
 
+    #[tokio::test]
+    async fn test_runner_run_registry() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("tcp://{}", listener.local_addr().unwrap())).unwrap();
+        let registry = Registry::new();
+
+        let runner = RunnerBuilder::new()
+            .target(Target::new("database".to_string(), vec![url.clone()]))
+            .registry(registry.clone())
+            .run_for_iterations(1)
+            .build();
+        runner.run().await.unwrap();
+
+        let report = registry.target("database").expect("target not reported");
+        assert_eq!(report.is_unavailable, false);
+        assert_eq!(report.urls[0].url, url.to_string());
+        assert_eq!(report.urls[0].available_streak, 1);
+        assert_eq!(report.urls[0].last_error, None);
+        assert!(report.urls[0].last_latency_ms.is_some());
+        assert!(report.urls[0].last_checked.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_runner_run_notifier() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/hook").json_body_partial(
+                r#"{"target": "database", "event": "available_to_unavailable", "url": "tcp://127.0.0.1:1"}"#,
+            );
+            then.status(200);
+        });
+        let notifier = Notifier::new();
+        notifier.set_channels(vec![Channel::Webhook(Webhook::new(server.url("/hook")))]);
+
+        let runner = RunnerBuilder::new()
+            .target(Target::new(
+                "database".to_string(),
+                vec![Url::parse("tcp://127.0.0.1:1").unwrap()],
+            ))
+            .failure_threshold(1)
+            .notifier(notifier)
+            .run_for_iterations(1)
+            .build();
+        runner.run().await.unwrap();
+
+        // The notification is delivered in the background.
+        for _ in 0..100 {
+            if mock.hits() > 0 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
         mock.assert();
     }
+
+    #[derive(Debug, Default)]
+    struct CountingProbe {
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl Probe for CountingProbe {
+        async fn probe(&self) -> ProbeResult {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            ProbeResult::err(std::time::Duration::from_millis(3), "queue is full")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_runner_run_custom_probe() {
+        let url = Url::parse("queue://jobs").unwrap();
+        let registry = Registry::new();
+
+        let runner = RunnerBuilder::new()
+            .target(Target::new("jobs".to_string(), vec![]))
+            .probe(url.clone(), CountingProbe::default())
+            .failure_threshold(2)
+            .wait_time_ms(1)
+            .registry(registry.clone())
+            .run_for_iterations(2)
+            .build();
+        runner.run().await.unwrap();
+
+        let report = registry.target("jobs").expect("target not reported");
+        assert_eq!(report.is_unavailable, true);
+        assert_eq!(report.urls[0].url, url.to_string());
+        assert_eq!(report.urls[0].last_error, Some("queue is full".to_string()));
+        assert_eq!(report.urls[0].last_latency_ms, Some(3.0));
+    }
+
+    #[tokio::test]
+    async fn test_runner_subscribe() {
+        let url = Url::parse("tcp://127.0.0.1:1").unwrap();
+        let runner = RunnerBuilder::new()
+            .target(Target::new("database".to_string(), vec![url.clone()]))
+            .failure_threshold(1)
+            .run_for_iterations(1)
+            .build();
+        let mut events = runner.subscribe();
+        runner.run().await.unwrap();
+
+        match events.try_recv().unwrap() {
+            RunnerEvent::UrlChecked {
+                target,
+                url: checked,
+                result,
+                event,
+                is_unavailable,
+                ..
+            } => {
+                assert_eq!(target, "database");
+                assert_eq!(checked, url);
+                assert_eq!(result.success, false);
+                assert_eq!(event, Event::AvailableToUnavailable);
+                assert_eq!(is_unavailable, true);
+            }
+            other => panic!("Expected UrlChecked, got {:?}", other),
+        }
+        match events.try_recv().unwrap() {
+            RunnerEvent::TargetChanged {
+                event,
+                available_urls,
+                total_urls,
+                ..
+            } => {
+                assert_eq!(event, Event::AvailableToUnavailable);
+                assert_eq!((available_urls, total_urls), (0, 1));
+            }
+            other => panic!("Expected TargetChanged, got {:?}", other),
+        }
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_runner_latency_threshold() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.path("/pay");
+            then.status(200)
+                .delay(std::time::Duration::from_millis(100));
+        });
+        let registry = Registry::new();
+        let runner = RunnerBuilder::new()
+            .target(Target::new(
+                "payments".to_string(),
+                vec![Url::parse(&server.url("/pay")).unwrap()],
+            ))
+            .failure_threshold(1)
+            .latency_threshold_ms(20)
+            .run_for_iterations(1)
+            .registry(registry.clone())
+            .build();
+        let mut events = runner.subscribe();
+        runner.run().await.unwrap();
+
+        match events.try_recv().unwrap() {
+            RunnerEvent::UrlChecked {
+                result,
+                event,
+                is_unavailable,
+                ..
+            } => {
+                assert_eq!(result.success, true);
+                assert_eq!(event, Event::AvailableToDegraded);
+                assert_eq!(is_unavailable, false);
+            }
+            other => panic!("Expected UrlChecked, got {:?}", other),
+        }
+        match events.try_recv().unwrap() {
+            RunnerEvent::TargetChanged { event, .. } => {
+                assert_eq!(event, Event::AvailableToDegraded)
+            }
+            other => panic!("Expected TargetChanged, got {:?}", other),
+        }
+
+        let report = registry.target("payments").unwrap();
+        assert_eq!(report.is_unavailable, false);
+        assert_eq!(report.is_degraded, true);
+        assert_eq!(report.urls[0].is_degraded, true);
+    }
+
+    #[derive(Debug)]
+    struct ExpiringProbe;
+
+    #[async_trait::async_trait]
+    impl Probe for ExpiringProbe {
+        async fn probe(&self) -> ProbeResult {
+            ProbeResult::ok(std::time::Duration::from_millis(3)).with_degraded(true)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_runner_degraded_probe() {
+        let url = Url::parse("tls://example.com").unwrap();
+        let registry = Registry::new();
+        let runner = RunnerBuilder::new()
+            .target(Target::new("certificate".to_string(), vec![url.clone()]))
+            .probe(url, ExpiringProbe)
+            .failure_threshold(1)
+            .run_for_iterations(1)
+            .registry(registry.clone())
+            .build();
+        let mut events = runner.subscribe();
+        runner.run().await.unwrap();
+
+        match events.try_recv().unwrap() {
+            RunnerEvent::UrlChecked { result, event, .. } => {
+                assert_eq!(result.success, true);
+                assert_eq!(event, Event::AvailableToDegraded);
+            }
+            other => panic!("Expected UrlChecked, got {:?}", other),
+        }
+
+        let report = registry.target("certificate").unwrap();
+        assert_eq!(report.is_unavailable, false);
+        assert_eq!(report.is_degraded, true);
+        assert_eq!(report.urls[0].last_error, None);
+    }
+
+    #[tokio::test]
+    async fn test_runner_run_until_cancelled() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("tcp://{}", listener.local_addr().unwrap())).unwrap();
+        let registry = Registry::new();
+
+        let runner = RunnerBuilder::new()
+            .target(Target::new("database".to_string(), vec![url]))
+            .wait_time_seconds(60)
+            .registry(registry.clone())
+            .build();
+        let shutdown = CancellationToken::new();
+        let handle = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { runner.run_until(shutdown).await }
+        });
+
+        for _ in 0..100 {
+            if registry.target("database").is_some() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        shutdown.cancel();
+
+        let result = tokio::time::timeout(std::time::Duration::from_secs(1), handle)
+            .await
+            .expect("runner did not stop");
+        assert!(result.unwrap().is_ok());
+        assert!(registry.target("database").unwrap().urls[0].available_streak > 0);
+    }
 }
@@ -1,15 +1,33 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
 use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
 
 /// Status is a struct that holds the status of a run.
 #[derive(Default, Debug)]
 pub struct Status {
-    pub threshold: u8,
+    pub failure_threshold: u8,
+    pub recovery_threshold: u8,
+    pub strategy: Strategy,
+    pub window: VecDeque<(DateTime<Utc>, bool)>,
     pub unavailable_count: i32,
     pub unavailable_started: DateTime<Utc>,
     pub unavailable_counted: u8,
     pub is_unavailable: bool,
     pub available_counted: u8,
     pub unavailable_events: Vec<Event>,
+    pub last_checked: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub last_latency_ms: Option<f64>,
+    /// Whether successful checks are slower than the latency threshold, cleared once the url is
+    /// unavailable so it is only degraded while it is not unavailable.
+    pub is_degraded: bool,
+    pub degraded_started: DateTime<Utc>,
+    pub degraded_count: i32,
+    pub slow_counted: u8,
+    pub fast_counted: u8,
 }
 
 impl Status {
@@ -17,66 +35,464 @@ impl Status {
     ///
     /// # Arguments
     ///
-    /// * `threshold`: A u8 that holds the threshold to determine if a target is
-    /// unavailable or available.
+    /// * `failure_threshold`: A u8 that holds the failures in a row to determine if a target is
+    /// unavailable.
+    /// * `recovery_threshold`: A u8 that holds the successes in a row to determine if an
+    /// unavailable target is available again.
     ///
     /// returns: Status
-    pub fn new(threshold: u8) -> Status {
+    pub fn new(
+        failure_threshold: u8,
+        recovery_threshold: u8,
+    ) -> Status {
         Status {
-            threshold,
+            failure_threshold,
+            recovery_threshold,
             ..Default::default()
         }
     }
 
+    /// Set the strategy used to determine if the target is unavailable or available.
+    ///
+    /// # Arguments
+    ///
+    /// * `strategy`: The strategy to evaluate checks with.
+    ///
+    /// returns: Status
+    pub fn with_strategy(
+        mut self,
+        strategy: Strategy,
+    ) -> Status {
+        self.strategy = strategy;
+        self
+    }
+
+    /// The state of the url derived from its checks.
+    pub fn state(&self) -> State {
+        State::new(self.is_unavailable, self.is_degraded)
+    }
+
+    /// Handle a successful check within the latency threshold.
     #[tracing::instrument(level = "trace")]
     pub fn handle_available(&mut self) -> Event {
-        self.available_counted += 1;
-        if self.is_unavailable {
-            if self.available_counted >= self.threshold {
-                self.is_unavailable = false;
-                self.unavailable_counted = 0;
-                let evt =
-                    Event::UnavailableToAvailable(chrono::Utc::now() - self.unavailable_started);
-                self.unavailable_events.push(evt);
-
-                return evt;
+        self.handle_success(false)
+    }
+
+    /// Handle a successful check slower than the latency threshold. The url is degraded after
+    /// `failure_threshold` slow checks in a row, and no longer degraded after
+    /// `recovery_threshold` checks in a row within the threshold.
+    #[tracing::instrument(level = "trace")]
+    pub fn handle_slow(&mut self) -> Event {
+        self.handle_success(true)
+    }
+
+    fn handle_success(
+        &mut self,
+        is_slow: bool,
+    ) -> Event {
+        let before = self.state();
+        let event = match self.strategy {
+            Strategy::Window { .. } => self.handle_window(true, Utc::now()),
+            Strategy::Consecutive => self.handle_consecutive_available(),
+        };
+
+        let now = Utc::now();
+        if is_slow {
+            self.slow_counted = self.slow_counted.saturating_add(1);
+            self.fast_counted = 0;
+            if !self.is_degraded && self.slow_counted >= self.failure_threshold {
+                self.is_degraded = true;
+                self.degraded_started = now;
+                self.degraded_count += 1;
+            }
+        } else {
+            self.fast_counted = self.fast_counted.saturating_add(1);
+            self.slow_counted = 0;
+            if self.is_degraded && self.fast_counted >= self.recovery_threshold {
+                self.is_degraded = false;
             }
         }
 
+        match (event, before, self.state()) {
+            (Event::UnavailableToAvailable(diff), _, State::Degraded) => {
+                Event::UnavailableToDegraded(diff)
+            }
+            (Event::NoChange, State::Available, State::Degraded) => Event::AvailableToDegraded,
+            (Event::NoChange, State::Degraded, State::Available) => {
+                Event::DegradedToAvailable(now - self.degraded_started)
+            }
+            (event, _, _) => event,
+        }
+    }
+
+    fn handle_consecutive_available(&mut self) -> Event {
+        self.available_counted = self.available_counted.saturating_add(1);
+        if self.is_unavailable && self.available_counted >= self.recovery_threshold {
+            self.is_unavailable = false;
+            self.unavailable_counted = 0;
+            let evt = Event::UnavailableToAvailable(chrono::Utc::now() - self.unavailable_started);
+            self.unavailable_events.push(evt);
+
+            return evt;
+        }
+
         self.unavailable_counted = 0;
 
         Event::NoChange
     }
 
+    /// Handle a failed check. A failure breaks the slow and fast streaks, but a degraded url
+    /// stays degraded until the failures make it unavailable.
     #[tracing::instrument(level = "trace")]
     pub fn handle_unavailable(&mut self) -> Event {
-        self.unavailable_counted += 1;
-        if !self.is_unavailable {
-            if self.unavailable_counted >= self.threshold {
-                self.is_unavailable = true;
-                self.available_counted = 0;
+        let before = self.state();
+        let event = match self.strategy {
+            Strategy::Window { .. } => self.handle_window(false, Utc::now()),
+            Strategy::Consecutive => self.handle_consecutive_unavailable(),
+        };
 
-                self.unavailable_started = chrono::Utc::now();
-                self.unavailable_count += 1;
+        self.slow_counted = 0;
+        self.fast_counted = 0;
+        if self.is_unavailable {
+            self.is_degraded = false;
+        }
 
-                return Event::AvailableToUnavailable;
-            }
+        match (event, before) {
+            (Event::AvailableToUnavailable, State::Degraded) => Event::DegradedToUnavailable,
+            (event, _) => event,
+        }
+    }
+
+    fn handle_consecutive_unavailable(&mut self) -> Event {
+        self.unavailable_counted = self.unavailable_counted.saturating_add(1);
+        if !self.is_unavailable && self.unavailable_counted >= self.failure_threshold {
+            self.is_unavailable = true;
+            self.available_counted = 0;
+
+            self.unavailable_started = chrono::Utc::now();
+            self.unavailable_count += 1;
+
+            return Event::AvailableToUnavailable;
         }
 
         self.available_counted = 0;
 
         Event::NoChange
     }
+
+    /// Record a check in the window and compare the failure ratio of the window against the
+    /// strategy's percentage. The target is only declared unavailable once the window holds at
+    /// least `failure_threshold` checks, so a single early failure does not trip it, and only
+    /// recovers after `recovery_threshold` successes in a row so it does not flap on the boundary.
+    fn handle_window(
+        &mut self,
+        is_available: bool,
+        now: DateTime<Utc>,
+    ) -> Event {
+        let Strategy::Window {
+            checks,
+            seconds,
+            failure_percent,
+        } = self.strategy
+        else {
+            return Event::NoChange;
+        };
+
+        self.window.push_back((now, is_available));
+        self.available_counted = if is_available {
+            self.available_counted.saturating_add(1)
+        } else {
+            0
+        };
+        if let Some(checks) = checks {
+            while self.window.len() > checks.max(1) {
+                self.window.pop_front();
+            }
+        }
+        if let Some(since) = seconds
+            .and_then(|seconds| TimeDelta::try_seconds(seconds as i64))
+            .and_then(|seconds| now.checked_sub_signed(seconds))
+        {
+            while self.window.front().is_some_and(|(at, _)| *at <= since) {
+                self.window.pop_front();
+            }
+        }
+
+        let failed = self.window.iter().filter(|(_, ok)| !ok).count();
+        let failed_percent = failed as f64 * 100.0 / self.window.len() as f64;
+
+        if !self.is_unavailable
+            && self.window.len() >= self.failure_threshold as usize
+            && failed_percent > failure_percent
+        {
+            self.is_unavailable = true;
+            self.unavailable_started = now;
+            self.unavailable_count += 1;
+
+            return Event::AvailableToUnavailable;
+        }
+
+        if self.is_unavailable
+            && failed_percent <= failure_percent
+            && self.available_counted >= self.recovery_threshold
+        {
+            self.is_unavailable = false;
+            let evt = Event::UnavailableToAvailable(now - self.unavailable_started);
+            self.unavailable_events.push(evt);
+
+            return evt;
+        }
+
+        Event::NoChange
+    }
 }
 
-/// Event is an enum that holds the event of a single run.
+/// Strategy is how the checks of a target are evaluated to determine if it is unavailable.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+pub enum Strategy {
+    /// Unavailable after `failure_threshold` failures in a row, available again after
+    /// `recovery_threshold` successes in a row.
+    #[default]
+    Consecutive,
+    /// Unavailable when more than `failure_percent` of the checks in the window failed, available
+    /// again once it drops back to `failure_percent` or below and the last `recovery_threshold`
+    /// checks succeeded. The window holds the last `checks` checks and/or the checks of the last
+    /// `seconds` seconds.
+    Window {
+        checks: Option<usize>,
+        seconds: Option<u64>,
+        failure_percent: f64,
+    },
+}
+
+/// Event is an enum that holds the event of a single run. The time deltas are how long the
+/// previous state lasted.
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub enum Event {
     AvailableToUnavailable,
     UnavailableToAvailable(TimeDelta),
+    AvailableToDegraded,
+    DegradedToAvailable(TimeDelta),
+    DegradedToUnavailable,
+    UnavailableToDegraded(TimeDelta),
     NoChange,
 }
 
+/// State is whether a url or target is available, available but slower than its latency
+/// threshold, or unavailable.
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum State {
+    #[default]
+    Available,
+    Degraded,
+    Unavailable,
+}
+
+impl State {
+    fn new(
+        is_unavailable: bool,
+        is_degraded: bool,
+    ) -> State {
+        match (is_unavailable, is_degraded) {
+            (true, _) => State::Unavailable,
+            (false, true) => State::Degraded,
+            (false, false) => State::Available,
+        }
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self {
+            State::Available => write!(f, "available"),
+            State::Degraded => write!(f, "degraded"),
+            State::Unavailable => write!(f, "unavailable"),
+        }
+    }
+}
+
+/// Quorum is how many urls of a target need to be available for the target to be available.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(try_from = "QuorumValue")]
+pub enum Quorum {
+    /// Available while at least one url is available.
+    #[default]
+    Any,
+    /// Available only while every url is available.
+    All,
+    /// Available while at least N urls are available.
+    Count(usize),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum QuorumValue {
+    Count(usize),
+    Name(String),
+}
+
+impl Quorum {
+    /// Whether the quorum is met.
+    ///
+    /// # Arguments
+    ///
+    /// * `available`: The number of available urls.
+    /// * `total`: The number of urls.
+    ///
+    /// returns: bool
+    pub fn is_met(
+        &self,
+        available: usize,
+        total: usize,
+    ) -> bool {
+        match *self {
+            Quorum::Any => available > 0,
+            Quorum::All => available == total,
+            Quorum::Count(count) => available >= count,
+        }
+    }
+}
+
+impl FromStr for Quorum {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(Quorum::Any),
+            "all" => Ok(Quorum::All),
+            _ => s
+                .parse()
+                .map(Quorum::Count)
+                .map_err(|_| format!("invalid quorum {}, expected any, all or a number", s)),
+        }
+    }
+}
+
+impl TryFrom<QuorumValue> for Quorum {
+    type Error = String;
+
+    fn try_from(value: QuorumValue) -> Result<Self, Self::Error> {
+        match value {
+            QuorumValue::Count(count) => Ok(Quorum::Count(count)),
+            QuorumValue::Name(name) => name.parse(),
+        }
+    }
+}
+
+impl fmt::Display for Quorum {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self {
+            Quorum::Any => write!(f, "any"),
+            Quorum::All => write!(f, "all"),
+            Quorum::Count(count) => write!(f, "{}", count),
+        }
+    }
+}
+
+/// TargetStatus holds the status of every url of a target, and the target status derived from
+/// them using the quorum.
+#[derive(Debug)]
+pub struct TargetStatus {
+    pub quorum: Quorum,
+    pub urls: Vec<Status>,
+    pub unavailable_count: i32,
+    pub unavailable_started: DateTime<Utc>,
+    pub is_unavailable: bool,
+    /// Whether the target is available but too few urls are within their latency threshold to
+    /// meet the quorum.
+    pub is_degraded: bool,
+    pub degraded_started: DateTime<Utc>,
+    pub degraded_count: i32,
+}
+
+impl TargetStatus {
+    /// Create a new TargetStatus.
+    ///
+    /// # Arguments
+    ///
+    /// * `quorum`: The quorum of urls needed for the target to be available.
+    /// * `urls`: The status of each url of the target.
+    ///
+    /// returns: TargetStatus
+    pub fn new(
+        quorum: Quorum,
+        urls: Vec<Status>,
+    ) -> TargetStatus {
+        TargetStatus {
+            quorum,
+            urls,
+            unavailable_count: 0,
+            unavailable_started: DateTime::default(),
+            is_unavailable: false,
+            is_degraded: false,
+            degraded_started: DateTime::default(),
+            degraded_count: 0,
+        }
+    }
+
+    /// The number of urls which are available.
+    pub fn available(&self) -> usize {
+        self.urls.iter().filter(|url| !url.is_unavailable).count()
+    }
+
+    /// The number of urls which are available and within their latency threshold.
+    pub fn healthy(&self) -> usize {
+        self.urls
+            .iter()
+            .filter(|url| url.state() == State::Available)
+            .count()
+    }
+
+    /// The state of the target derived from its urls.
+    pub fn state(&self) -> State {
+        State::new(self.is_unavailable, self.is_degraded)
+    }
+
+    /// Derive the target status from the url statuses, returning the event if it changed.
+    #[tracing::instrument(level = "trace")]
+    pub fn evaluate(&mut self) -> Event {
+        let now = Utc::now();
+        let is_available = self.quorum.is_met(self.available(), self.urls.len());
+        let is_degraded = is_available && !self.quorum.is_met(self.healthy(), self.urls.len());
+
+        let before = self.state();
+        if !self.is_unavailable && !is_available {
+            self.unavailable_started = now;
+            self.unavailable_count += 1;
+        }
+        if !self.is_degraded && is_degraded {
+            self.degraded_started = now;
+            self.degraded_count += 1;
+        }
+        self.is_unavailable = !is_available;
+        self.is_degraded = is_degraded;
+
+        match (before, self.state()) {
+            (State::Available, State::Unavailable) => Event::AvailableToUnavailable,
+            (State::Unavailable, State::Available) => {
+                Event::UnavailableToAvailable(now - self.unavailable_started)
+            }
+            (State::Available, State::Degraded) => Event::AvailableToDegraded,
+            (State::Degraded, State::Available) => {
+                Event::DegradedToAvailable(now - self.degraded_started)
+            }
+            (State::Degraded, State::Unavailable) => Event::DegradedToUnavailable,
+            (State::Unavailable, State::Degraded) => {
+                Event::UnavailableToDegraded(now - self.unavailable_started)
+            }
+            _ => Event::NoChange,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use pretty_assertions::assert_eq;
@@ -85,8 +501,9 @@ mod tests {
 
     #[test]
     fn test_status_new() {
-        let status = Status::new(3);
-        assert_eq!(status.threshold, 3);
+        let status = Status::new(3, 10);
+        assert_eq!(status.failure_threshold, 3);
+        assert_eq!(status.recovery_threshold, 10);
         assert_eq!(status.unavailable_count, 0);
         assert_eq!(status.unavailable_counted, 0);
         assert_eq!(status.is_unavailable, false);
@@ -96,7 +513,7 @@ mod tests {
 
     #[test]
     fn test_status_handle_available() {
-        let mut status = Status::new(3);
+        let mut status = Status::new(3, 3);
         status.is_unavailable = true;
         status.unavailable_started = chrono::Utc::now();
         assert_eq!(status.handle_available(), Event::NoChange);
@@ -111,7 +528,7 @@ mod tests {
 
     #[test]
     fn test_status_handle_unavailable() {
-        let mut status = Status::new(3);
+        let mut status = Status::new(3, 3);
         assert_eq!(status.handle_available(), Event::NoChange);
         assert_eq!(status.handle_available(), Event::NoChange);
         assert_eq!(status.handle_available(), Event::NoChange);
@@ -119,4 +536,306 @@ mod tests {
         assert_eq!(status.handle_unavailable(), Event::NoChange);
         assert_eq!(status.handle_unavailable(), Event::AvailableToUnavailable);
     }
+
+    #[test]
+    fn test_status_separate_thresholds() {
+        let mut status = Status::new(3, 10);
+        assert_eq!(status.handle_unavailable(), Event::NoChange);
+        assert_eq!(status.handle_unavailable(), Event::NoChange);
+        assert_eq!(status.handle_unavailable(), Event::AvailableToUnavailable);
+
+        for _ in 0..9 {
+            assert_eq!(status.handle_available(), Event::NoChange);
+        }
+        assert!(matches!(
+            status.handle_available(),
+            Event::UnavailableToAvailable(_)
+        ));
+    }
+
+    #[test]
+    fn test_status_recovery_reset_by_failure() {
+        let mut status = Status::new(1, 3);
+        assert_eq!(status.handle_unavailable(), Event::AvailableToUnavailable);
+        assert_eq!(status.handle_available(), Event::NoChange);
+        assert_eq!(status.handle_available(), Event::NoChange);
+        assert_eq!(status.handle_unavailable(), Event::NoChange);
+        assert_eq!(status.handle_available(), Event::NoChange);
+        assert_eq!(status.handle_available(), Event::NoChange);
+        assert!(status.is_unavailable);
+    }
+
+    #[test]
+    fn test_status_handle_slow() {
+        let mut status = Status::new(2, 2);
+        assert_eq!(status.handle_slow(), Event::NoChange);
+        assert_eq!(status.handle_slow(), Event::AvailableToDegraded);
+        assert_eq!(status.state(), State::Degraded);
+        assert_eq!(status.degraded_count, 1);
+
+        // A fast check resets the slow streak but the url stays degraded until it recovers.
+        assert_eq!(status.handle_available(), Event::NoChange);
+        assert_eq!(status.handle_slow(), Event::NoChange);
+        assert_eq!(status.handle_available(), Event::NoChange);
+        assert!(matches!(
+            status.handle_available(),
+            Event::DegradedToAvailable(_)
+        ));
+        assert_eq!(status.state(), State::Available);
+    }
+
+    #[test]
+    fn test_status_degraded_unavailable() {
+        let mut status = Status::new(1, 1);
+        assert_eq!(status.handle_slow(), Event::AvailableToDegraded);
+        assert_eq!(status.handle_unavailable(), Event::DegradedToUnavailable);
+        assert_eq!(status.state(), State::Unavailable);
+        assert!(matches!(
+            status.handle_slow(),
+            Event::UnavailableToDegraded(_)
+        ));
+        assert_eq!(status.handle_unavailable(), Event::DegradedToUnavailable);
+        assert!(matches!(
+            status.handle_available(),
+            Event::UnavailableToAvailable(_)
+        ));
+        assert_eq!(status.unavailable_events.len(), 2);
+    }
+
+    #[test]
+    fn test_status_failure_while_degraded() {
+        let mut status = Status::new(5, 1);
+        for _ in 0..4 {
+            status.handle_slow();
+        }
+        assert_eq!(status.handle_slow(), Event::AvailableToDegraded);
+        assert_eq!(status.handle_unavailable(), Event::NoChange);
+        assert_eq!(status.state(), State::Degraded);
+
+        let mut status = Status::new(2, 1);
+        status.handle_slow();
+        assert_eq!(status.handle_slow(), Event::AvailableToDegraded);
+        assert_eq!(status.handle_unavailable(), Event::NoChange);
+        assert_eq!(status.state(), State::Degraded);
+        assert_eq!(status.handle_unavailable(), Event::DegradedToUnavailable);
+        assert_eq!(status.is_degraded, false);
+        assert!(matches!(
+            status.handle_available(),
+            Event::UnavailableToAvailable(_)
+        ));
+        assert_eq!(status.state(), State::Available);
+
+        status.handle_slow();
+        status.handle_unavailable();
+        assert_eq!(status.handle_slow(), Event::NoChange);
+        assert_eq!(status.state(), State::Available);
+        assert_eq!(status.slow_counted, 1);
+    }
+
+    fn window(
+        checks: Option<usize>,
+        seconds: Option<u64>,
+        failure_percent: f64,
+    ) -> Strategy {
+        Strategy::Window {
+            checks,
+            seconds,
+            failure_percent,
+        }
+    }
+
+    #[test]
+    fn test_status_window_alternating_failures() {
+        let mut status = Status::new(3, 2).with_strategy(window(Some(10), None, 40.0));
+        let now = Utc::now();
+
+        let events: Vec<Event> = (0..20)
+            .map(|i| status.handle_window(i % 2 == 0, now))
+            .collect();
+
+        assert_eq!(events[3], Event::AvailableToUnavailable);
+        assert_eq!(
+            events.iter().filter(|evt| **evt != Event::NoChange).count(),
+            1
+        );
+        assert!(status.is_unavailable);
+        assert_eq!(status.window.len(), 10);
+    }
+
+    #[test]
+    fn test_status_window_needs_failure_threshold_checks() {
+        let mut status = Status::new(3, 3).with_strategy(window(Some(10), None, 50.0));
+        let now = Utc::now();
+
+        assert_eq!(status.handle_window(false, now), Event::NoChange);
+        assert_eq!(status.handle_window(false, now), Event::NoChange);
+        assert_eq!(
+            status.handle_window(false, now),
+            Event::AvailableToUnavailable
+        );
+        assert_eq!(status.unavailable_count, 1);
+    }
+
+    #[test]
+    fn test_status_window_checks_recovery() {
+        let mut status = Status::new(1, 1).with_strategy(window(Some(4), None, 50.0));
+        let now = Utc::now();
+
+        assert_eq!(
+            status.handle_window(false, now),
+            Event::AvailableToUnavailable
+        );
+        assert_eq!(status.handle_window(false, now), Event::NoChange);
+        assert_eq!(status.handle_window(true, now), Event::NoChange);
+        assert!(matches!(
+            status.handle_window(true, now),
+            Event::UnavailableToAvailable(_)
+        ));
+        assert_eq!(status.window.len(), 4);
+
+        // The window only holds the last 4 checks, so the first failures drop out.
+        assert_eq!(status.handle_window(false, now), Event::NoChange);
+        assert_eq!(status.handle_window(true, now), Event::NoChange);
+        assert_eq!(status.window.iter().filter(|(_, ok)| !ok).count(), 1);
+        assert_eq!(status.unavailable_events.len(), 1);
+    }
+
+    #[test]
+    fn test_status_window_seconds() {
+        let mut status = Status::new(1, 1).with_strategy(window(None, Some(60), 50.0));
+        let start = Utc::now();
+        let after = |seconds| start + TimeDelta::try_seconds(seconds).unwrap();
+
+        assert_eq!(status.handle_window(true, start), Event::NoChange);
+        assert_eq!(status.handle_window(true, after(10)), Event::NoChange);
+        assert_eq!(status.handle_window(false, after(20)), Event::NoChange);
+        assert_eq!(status.window.len(), 3);
+
+        // The successes at 0s and 10s fall out of the window, leaving only the failures.
+        assert_eq!(
+            status.handle_window(false, after(70)),
+            Event::AvailableToUnavailable
+        );
+        assert_eq!(status.window.len(), 2);
+        assert_eq!(
+            status.handle_window(true, after(90)),
+            Event::UnavailableToAvailable(TimeDelta::try_seconds(20).unwrap())
+        );
+    }
+
+    fn url_status(is_unavailable: bool) -> Status {
+        Status {
+            is_unavailable,
+            ..Status::new(1, 1)
+        }
+    }
+
+    #[test]
+    fn test_quorum_is_met() {
+        assert!(Quorum::Any.is_met(1, 3));
+        assert!(!Quorum::Any.is_met(0, 3));
+        assert!(Quorum::All.is_met(3, 3));
+        assert!(!Quorum::All.is_met(2, 3));
+        assert!(Quorum::Count(2).is_met(2, 3));
+        assert!(!Quorum::Count(2).is_met(1, 3));
+    }
+
+    #[test]
+    fn test_quorum_from_str() {
+        assert_eq!("any".parse::<Quorum>(), Ok(Quorum::Any));
+        assert_eq!("all".parse::<Quorum>(), Ok(Quorum::All));
+        assert_eq!("2".parse::<Quorum>(), Ok(Quorum::Count(2)));
+        assert_eq!(
+            "most".parse::<Quorum>(),
+            Err("invalid quorum most, expected any, all or a number".to_string())
+        );
+        assert_eq!(Quorum::Count(2).to_string(), "2");
+    }
+
+    #[test]
+    fn test_quorum_deserialize() {
+        let quorums: Vec<Quorum> = serde_yaml::from_str("[any, all, 2, \"3\"]").unwrap();
+        assert_eq!(
+            quorums,
+            vec![Quorum::Any, Quorum::All, Quorum::Count(2), Quorum::Count(3)]
+        );
+        assert!(serde_yaml::from_str::<Quorum>("most").is_err());
+    }
+
+    #[test]
+    fn test_target_status_evaluate_any() {
+        let mut status = TargetStatus::new(Quorum::Any, vec![url_status(false), url_status(true)]);
+        assert_eq!(status.evaluate(), Event::NoChange);
+        assert_eq!(status.available(), 1);
+
+        status.urls[0].is_unavailable = true;
+        assert_eq!(status.evaluate(), Event::AvailableToUnavailable);
+        assert_eq!(status.evaluate(), Event::NoChange);
+        assert_eq!(status.unavailable_count, 1);
+
+        status.urls[1].is_unavailable = false;
+        assert!(matches!(
+            status.evaluate(),
+            Event::UnavailableToAvailable(_)
+        ));
+        assert_eq!(status.is_unavailable, false);
+    }
+
+    #[test]
+    fn test_target_status_evaluate_all() {
+        let mut status = TargetStatus::new(Quorum::All, vec![url_status(false), url_status(true)]);
+        assert_eq!(status.evaluate(), Event::AvailableToUnavailable);
+        assert_eq!(status.is_unavailable, true);
+    }
+
+    #[test]
+    fn test_target_status_evaluate_count() {
+        let mut status = TargetStatus::new(
+            Quorum::Count(2),
+            vec![url_status(false), url_status(false), url_status(true)],
+        );
+        assert_eq!(status.evaluate(), Event::NoChange);
+
+        status.urls[1].is_unavailable = true;
+        assert_eq!(status.evaluate(), Event::AvailableToUnavailable);
+    }
+
+    #[test]
+    fn test_target_status_evaluate_degraded() {
+        let slow = || {
+            let mut status = url_status(false);
+            status.handle_slow();
+            status
+        };
+        let mut status = TargetStatus::new(Quorum::Any, vec![slow(), url_status(false)]);
+        assert_eq!(status.evaluate(), Event::NoChange);
+        assert_eq!(status.healthy(), 1);
+
+        status.urls[1] = slow();
+        assert_eq!(status.evaluate(), Event::AvailableToDegraded);
+        assert_eq!(status.state(), State::Degraded);
+        assert_eq!(status.degraded_count, 1);
+
+        status.urls[0].is_unavailable = true;
+        status.urls[1].is_unavailable = true;
+        assert_eq!(status.evaluate(), Event::DegradedToUnavailable);
+        assert_eq!(status.is_degraded, false);
+
+        status.urls[0].is_unavailable = false;
+        assert!(matches!(status.evaluate(), Event::UnavailableToDegraded(_)));
+
+        status.urls[0] = url_status(false);
+        assert!(matches!(status.evaluate(), Event::DegradedToAvailable(_)));
+        assert_eq!(status.state(), State::Available);
+    }
+
+    #[test]
+    fn test_status_streak_saturates() {
+        let mut status = Status::new(3, 3);
+        for _ in 0..300 {
+            status.handle_unavailable();
+        }
+        assert_eq!(status.unavailable_counted, u8::MAX);
+        assert_eq!(status.unavailable_count, 1);
+    }
 }
@@ -1,10 +1,50 @@
 use core::fmt;
-use std::ffi::OsString;
+use std::collections::HashSet;
 use std::str::FromStr;
 
 use reqwest::Url;
+use thiserror::Error;
 
 use crate::runner::url::vec_to_string;
+use crate::runner::{dns, tcp, tls, Error};
+
+/// The scheme used for urls given without one.
+pub const DEFAULT_SCHEME: &str = "https";
+
+/// The url schemes a Target parsed from a string can be checked with.
+pub const SUPPORTED_SCHEMES: [&str; 5] = [
+    "http",
+    DEFAULT_SCHEME,
+    tcp::SCHEME_TCP,
+    dns::SCHEME_DNS,
+    tls::SCHEME_TLS,
+];
+
+/// TargetParseError is why a `name=url,url` string is not a valid Target.
+#[derive(Error, Debug, PartialEq)]
+pub enum TargetParseError {
+    #[error("missing target name in {input:?}, expected name=url[,url...]")]
+    MissingName { input: String },
+    #[error("target {name} has no urls, expected {name}=url[,url...]")]
+    EmptyUrls { name: String },
+    #[error("target {name}: invalid url {url}: {reason}")]
+    InvalidUrl {
+        name: String,
+        url: String,
+        reason: String,
+    },
+    #[error(
+        "target {name}: unsupported scheme {scheme} in {url}, expected one of {}",
+        SUPPORTED_SCHEMES.join(", ")
+    )]
+    UnsupportedScheme {
+        name: String,
+        url: String,
+        scheme: String,
+    },
+    #[error("duplicate target name {name}, each target needs a unique name")]
+    DuplicateName { name: String },
+}
 
 /// Target is a struct that holds the name and urls of a target.
 #[derive(Clone, PartialEq)]
@@ -36,8 +76,53 @@ impl Target {
     }
 }
 
+/// Check that no two targets share a name.
+///
+/// # Arguments
+///
+/// * `targets`: The targets to check.
+///
+/// returns: Result<(), TargetParseError>
+pub fn validate_names(targets: &[Target]) -> Result<(), TargetParseError> {
+    let mut names = HashSet::new();
+    for target in targets {
+        if !names.insert(target.name.as_str()) {
+            return Err(TargetParseError::DuplicateName {
+                name: target.name.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse a url, prefixing it with the scheme when it has none.
+///
+/// # Arguments
+///
+/// * `url`: The url to parse, surrounding whitespace is ignored.
+/// * `scheme`: The scheme to use when the url has none.
+///
+/// returns: Result<Url, Error>
+pub fn parse_url(
+    url: &str,
+    scheme: &str,
+) -> Result<Url, Error> {
+    let url = url.trim();
+    let url = if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("{}://{}", scheme, url)
+    };
+
+    Url::parse(url.as_str()).map_err(|err| Error::InvalidUrl {
+        url,
+        reason: err.to_string(),
+    })
+}
+
 impl FromStr for Target {
-    type Err = ();
+    type Err = TargetParseError;
 
     /// Create a Target from a string.
     ///
@@ -55,30 +140,49 @@ impl FromStr for Target {
     /// let target = Target::from_str("external=https://example.com,https://example2.com").unwrap();
     /// ```
     fn from_str(str: &str) -> Result<Self, Self::Err> {
-        match str.split_once("=") {
-            Some((name, urls)) => {
-                let urls = urls
-                    .split(",")
-                    .map(|url| {
-                        let u = if url.trim().starts_with("http") {
-                            url.trim().to_string()
-                        } else {
-                            format!("https://{}", url.trim())
-                        };
-
-                        Url::parse(u.as_str()).unwrap()
-                    })
-                    .collect();
-                Ok(Target::new(name.to_string(), urls))
+        let (name, urls) = match str.split_once('=') {
+            Some((name, urls)) if !name.trim().is_empty() => (name.trim(), urls),
+            _ => {
+                return Err(TargetParseError::MissingName {
+                    input: str.to_string(),
+                })
             }
-            None => Err(()),
+        };
+
+        let urls = urls
+            .split(',')
+            .filter(|url| !url.trim().is_empty())
+            .map(|url| {
+                let url = parse_url(url, DEFAULT_SCHEME).map_err(|err| match err {
+                    Error::InvalidUrl { url, reason } => TargetParseError::InvalidUrl {
+                        name: name.to_string(),
+                        url,
+                        reason,
+                    },
+                    err => TargetParseError::InvalidUrl {
+                        name: name.to_string(),
+                        url: url.to_string(),
+                        reason: err.to_string(),
+                    },
+                })?;
+                if !SUPPORTED_SCHEMES.contains(&url.scheme()) {
+                    return Err(TargetParseError::UnsupportedScheme {
+                        name: name.to_string(),
+                        scheme: url.scheme().to_string(),
+                        url: url.to_string(),
+                    });
+                }
+
+                Ok(url)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if urls.is_empty() {
+            return Err(TargetParseError::EmptyUrls {
+                name: name.to_string(),
+            });
         }
-    }
-}
 
-impl From<OsString> for Target {
-    fn from(str: OsString) -> Self {
-        Target::from_str(str.to_str().unwrap()).unwrap()
+        Ok(Target::new(name.to_string(), urls))
     }
 }
 
@@ -135,20 +239,77 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_target_from_str_tcp() {
+        let target = Target::from_str("db=tcp://db.internal:5432").expect("failed to parse");
+        assert_eq!(target.name, "db");
+        assert_eq!(
+            target.urls[0],
+            Url::parse("tcp://db.internal:5432").expect("failed to parse url")
+        );
+    }
+
     #[test]
     fn test_target_from_str_fail() {
-        let target = Target::from_str("external");
-        assert_eq!(target.err(), Some(()));
+        assert_eq!(
+            Target::from_str("external"),
+            Err(TargetParseError::MissingName {
+                input: "external".to_string()
+            })
+        );
+        assert_eq!(
+            Target::from_str("=https://example.com"),
+            Err(TargetParseError::MissingName {
+                input: "=https://example.com".to_string()
+            })
+        );
+        assert_eq!(
+            Target::from_str("external= , "),
+            Err(TargetParseError::EmptyUrls {
+                name: "external".to_string()
+            })
+        );
+        assert_eq!(
+            Target::from_str("external=https://exa mple.com"),
+            Err(TargetParseError::InvalidUrl {
+                name: "external".to_string(),
+                url: "https://exa mple.com".to_string(),
+                reason: "invalid domain character".to_string(),
+            })
+        );
+        assert_eq!(
+            Target::from_str("external=ftp://example.com")
+                .unwrap_err()
+                .to_string(),
+            "target external: unsupported scheme ftp in ftp://example.com/, expected one of http, \
+             https, tcp, dns, tls"
+        );
     }
 
     #[test]
-    fn test_target_from_os_string() {
-        let target = Target::from(OsString::from("external=https://example.com"));
-        assert_eq!(target.name, "external");
+    fn test_validate_names() {
+        let target = |name: &str| Target::from_str(&format!("{}=example.com", name)).unwrap();
+
+        assert_eq!(validate_names(&[target("a"), target("b")]), Ok(()));
         assert_eq!(
-            target.urls[0],
-            Url::parse("https://example.com").expect("failed to parse url")
+            validate_names(&[target("a"), target("b"), target("a")]),
+            Err(TargetParseError::DuplicateName {
+                name: "a".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_url() {
+        assert_eq!(
+            parse_url(" db.internal:5432 ", "tcp").expect("failed to parse url"),
+            Url::parse("tcp://db.internal:5432").expect("failed to parse url")
+        );
+        assert_eq!(
+            parse_url("http://example.com", "tcp").expect("failed to parse url"),
+            Url::parse("http://example.com").expect("failed to parse url")
         );
+        assert!(parse_url("http://", "https").is_err());
     }
 
     #[test]
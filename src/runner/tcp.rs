@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use reqwest::Url;
+use tokio::net::TcpStream;
+use tokio::time;
+use url::Host;
+
+use crate::runner::Error;
+
+pub const SCHEME_TCP: &str = "tcp";
+
+/// Open a raw TCP connection to the host and port of the url.
+///
+/// # Arguments
+///
+/// * `url`: The `tcp://host:port` url to connect to.
+/// * `connect_timeout_ms`: Connect timeout in milliseconds to be considered a failure.
+///
+/// returns: Result<(), Error>
+pub async fn connect(
+    url: &Url,
+    connect_timeout_ms: u64,
+) -> Result<(), Error> {
+    // host_str keeps the brackets around IPv6 addresses, which do not parse as an address.
+    let host = match url.host() {
+        Some(Host::Domain(domain)) => domain.to_string(),
+        Some(Host::Ipv4(ip)) => ip.to_string(),
+        Some(Host::Ipv6(ip)) => ip.to_string(),
+        None => {
+            return Err(Error::InvalidUrl {
+                url: url.to_string(),
+                reason: "missing host".to_string(),
+            })
+        }
+    };
+    let port = url.port().ok_or_else(|| Error::InvalidUrl {
+        url: url.to_string(),
+        reason: "missing port".to_string(),
+    })?;
+
+    let stream = time::timeout(
+        Duration::from_millis(connect_timeout_ms),
+        TcpStream::connect((host.as_str(), port)),
+    )
+    .await
+    .map_err(|_| Error::TimeoutError {
+        timeout_ms: connect_timeout_ms,
+    })??;
+
+    drop(stream);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tcp_connect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let url = Url::parse(&format!("tcp://{}", addr)).unwrap();
+
+        assert!(connect(&url, 1000).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_tcp_connect_ipv6() {
+        let Ok(listener) = TcpListener::bind("[::1]:0").await else {
+            eprintln!("skipping, IPv6 loopback is not available");
+            return;
+        };
+        let addr = listener.local_addr().unwrap();
+        let url = Url::parse(&format!("tcp://[::1]:{}", addr.port())).unwrap();
+
+        assert!(connect(&url, 1000).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_tcp_connect_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let url = Url::parse(&format!("tcp://{}", addr)).unwrap();
+
+        match connect(&url, 1000).await {
+            Err(Error::IoError { .. }) => {}
+            other => panic!("Expected IoError, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tcp_connect_missing_port() {
+        let url = Url::parse("tcp://127.0.0.1").unwrap();
+
+        match connect(&url, 1000).await {
+            Err(Error::InvalidUrl { reason, .. }) => assert_eq!(reason, "missing port"),
+            other => panic!("Expected InvalidUrl, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,492 @@
+use std::net::IpAddr;
+use std::time::Duration;
+
+use reqwest::Url;
+use tokio::net::TcpStream;
+use tokio::time;
+use x509_parser::extensions::GeneralName;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+use crate::runner::{Error, TlsOptions};
+
+pub const SCHEME_TLS: &str = "tls";
+pub const DEFAULT_EXPIRY_THRESHOLD_DAYS: f64 = 14.0;
+
+const DEFAULT_TLS_PORT: u16 = 443;
+const QUERY_EXPIRY_THRESHOLD_DAYS: &str = "expiry_threshold_days";
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+/// Handshake is a TLS handshake described by a `tls://` url.
+///
+/// The url takes the form `tls://host[:port]?expiry_threshold_days=14`, where the port defaults
+/// to 443.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Handshake {
+    pub host: String,
+    pub port: u16,
+    pub expiry_threshold_days: f64,
+}
+
+/// Certificate holds what was learnt about the certificate presented during a handshake.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Certificate {
+    pub days_until_expiry: f64,
+    pub issuer: String,
+    pub subject_alternative_names: Vec<String>,
+    pub san_match: bool,
+    pub chain_valid: bool,
+}
+
+impl Handshake {
+    /// Create a Handshake from a `tls://` url.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The url describing the handshake.
+    ///
+    /// returns: Result<Handshake, Error>
+    pub fn from_url(url: &Url) -> Result<Handshake, Error> {
+        let invalid = |reason: &str| Error::InvalidUrl {
+            url: url.to_string(),
+            reason: reason.to_string(),
+        };
+
+        let host = url
+            .host_str()
+            .filter(|host| !host.is_empty())
+            .ok_or_else(|| invalid("missing host"))?;
+
+        let mut handshake = Handshake {
+            host: host
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_string(),
+            port: url.port().unwrap_or(DEFAULT_TLS_PORT),
+            expiry_threshold_days: DEFAULT_EXPIRY_THRESHOLD_DAYS,
+        };
+
+        for (key, value) in url.query_pairs() {
+            if key == QUERY_EXPIRY_THRESHOLD_DAYS {
+                handshake.expiry_threshold_days = value
+                    .parse()
+                    .map_err(|_| invalid("expiry_threshold_days must be a number"))?;
+            }
+        }
+
+        Ok(handshake)
+    }
+
+    /// Perform the handshake and inspect the certificate presented by the server.
+    ///
+    /// The chain is first verified against the system roots and the root certificates of the TLS
+    /// options, if that fails the handshake is retried without verification so the certificate
+    /// can still be reported on. The server name of the options is sent as SNI and matched
+    /// against the certificate instead of the host.
+    ///
+    /// # Arguments
+    ///
+    /// * `tls`: The TLS options of the handshake.
+    /// * `connect_timeout_ms`: Connect timeout in milliseconds to be considered a failure.
+    /// * `timeout_ms`: Handshake timeout in milliseconds to be considered a failure.
+    ///
+    /// returns: Result<Certificate, Error>
+    pub async fn inspect(
+        &self,
+        tls: &TlsOptions,
+        connect_timeout_ms: u64,
+        timeout_ms: u64,
+    ) -> Result<Certificate, Error> {
+        let server_name = tls.server_name.as_deref().unwrap_or(&self.host);
+        let (der, chain_valid) = match self
+            .peer_certificate(tls, server_name, true, connect_timeout_ms, timeout_ms)
+            .await
+        {
+            Ok(der) => (der, true),
+            Err(Error::TlsError { .. }) => (
+                self.peer_certificate(tls, server_name, false, connect_timeout_ms, timeout_ms)
+                    .await?,
+                false,
+            ),
+            Err(err) => return Err(err),
+        };
+
+        let (_, cert) =
+            X509Certificate::from_der(&der).map_err(|err| Error::TlsCertificateInvalid {
+                reason: format!("failed to parse certificate: {}", err),
+            })?;
+
+        let subject_alternative_names = subject_alternative_names(&cert);
+        let san_match = subject_alternative_names
+            .iter()
+            .any(|name| matches_host(name, server_name));
+
+        Ok(Certificate {
+            days_until_expiry: (cert.validity().not_after.timestamp()
+                - chrono::Utc::now().timestamp()) as f64
+                / SECONDS_PER_DAY,
+            issuer: cert.issuer().to_string(),
+            subject_alternative_names,
+            san_match,
+            chain_valid,
+        })
+    }
+
+    async fn peer_certificate(
+        &self,
+        tls: &TlsOptions,
+        server_name: &str,
+        verify: bool,
+        connect_timeout_ms: u64,
+        timeout_ms: u64,
+    ) -> Result<Vec<u8>, Error> {
+        // Hostnames are matched against the certificate separately so a mismatch is reported
+        // on its own rather than as an untrusted chain.
+        let connector = tls
+            .builder()
+            .danger_accept_invalid_hostnames(true)
+            .danger_accept_invalid_certs(!verify)
+            .build()?;
+        let connector = tokio_native_tls::TlsConnector::from(connector);
+
+        let stream = time::timeout(
+            Duration::from_millis(connect_timeout_ms),
+            TcpStream::connect((self.host.as_str(), self.port)),
+        )
+        .await
+        .map_err(|_| Error::TimeoutError {
+            timeout_ms: connect_timeout_ms,
+        })??;
+
+        let stream = time::timeout(
+            Duration::from_millis(timeout_ms),
+            connector.connect(server_name, stream),
+        )
+        .await
+        .map_err(|_| Error::TimeoutError { timeout_ms })??;
+
+        let cert =
+            stream
+                .get_ref()
+                .peer_certificate()?
+                .ok_or_else(|| Error::TlsCertificateInvalid {
+                    reason: "no certificate presented".to_string(),
+                })?;
+
+        Ok(cert.to_der()?)
+    }
+}
+
+impl Certificate {
+    /// Whether the certificate expires within the threshold.
+    pub fn is_expiring(
+        &self,
+        threshold_days: f64,
+    ) -> bool {
+        self.days_until_expiry < threshold_days
+    }
+
+    /// Verify the certificate would be accepted by a client connecting to the host.
+    pub fn verify(&self) -> Result<(), Error> {
+        let reason = if self.days_until_expiry <= 0.0 {
+            "certificate has expired"
+        } else if !self.chain_valid {
+            "certificate chain is not trusted"
+        } else if !self.san_match {
+            "certificate does not match host"
+        } else {
+            return Ok(());
+        };
+
+        Err(Error::TlsCertificateInvalid {
+            reason: reason.to_string(),
+        })
+    }
+}
+
+fn subject_alternative_names(cert: &X509Certificate) -> Vec<String> {
+    let Ok(Some(san)) = cert.subject_alternative_name() else {
+        return vec![];
+    };
+
+    san.value
+        .general_names
+        .iter()
+        .filter_map(|name| match name {
+            GeneralName::DNSName(dns) => Some(dns.to_string()),
+            GeneralName::IPAddress(octets) => match octets.len() {
+                4 => Some(IpAddr::from(<[u8; 4]>::try_from(*octets).ok()?).to_string()),
+                16 => Some(IpAddr::from(<[u8; 16]>::try_from(*octets).ok()?).to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Match a host against a subject alternative name, allowing a single leading wildcard label.
+fn matches_host(
+    name: &str,
+    host: &str,
+) -> bool {
+    let name = name.to_lowercase();
+    let host = host.to_lowercase();
+
+    match name.strip_prefix("*.") {
+        Some(suffix) => host
+            .split_once('.')
+            .map_or(false, |(label, rest)| !label.is_empty() && rest == suffix),
+        None => name == host,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use chrono::Datelike;
+    use pretty_assertions::assert_eq;
+    use rcgen::{BasicConstraints, CertificateParams, DistinguishedName, DnType, IsCa};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::runner::certificates_from_pem;
+
+    /// Start a TLS server presenting a certificate for the subject alternative names, signed by
+    /// the CA or else self signed.
+    async fn tls_server(
+        params: CertificateParams,
+        ca: Option<&rcgen::Certificate>,
+    ) -> SocketAddr {
+        let cert = rcgen::Certificate::from_params(params).unwrap();
+        let pem = match ca {
+            Some(ca) => cert.serialize_pem_with_signer(ca).unwrap(),
+            None => cert.serialize_pem().unwrap(),
+        };
+        let identity = native_tls::Identity::from_pkcs8(
+            pem.as_bytes(),
+            cert.serialize_private_key_pem().as_bytes(),
+        )
+        .unwrap();
+        let acceptor = tokio_native_tls::TlsAcceptor::from(
+            native_tls::TlsAcceptor::builder(identity).build().unwrap(),
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let _ = acceptor.accept(stream).await;
+                });
+            }
+        });
+
+        addr
+    }
+
+    fn params(names: Vec<&str>) -> CertificateParams {
+        let mut params =
+            CertificateParams::new(names.into_iter().map(String::from).collect::<Vec<_>>());
+        let mut distinguished_name = DistinguishedName::new();
+        distinguished_name.push(DnType::CommonName, "netcheck test ca");
+        params.distinguished_name = distinguished_name;
+        params
+    }
+
+    #[test]
+    fn test_handshake_from_url() {
+        let url = Url::parse("tls://example.com:8443?expiry_threshold_days=30").unwrap();
+
+        assert_eq!(
+            Handshake::from_url(&url).unwrap(),
+            Handshake {
+                host: "example.com".to_string(),
+                port: 8443,
+                expiry_threshold_days: 30.0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_handshake_from_url_defaults() {
+        let url = Url::parse("tls://example.com").unwrap();
+        let handshake = Handshake::from_url(&url).unwrap();
+
+        assert_eq!(handshake.port, 443);
+        assert_eq!(
+            handshake.expiry_threshold_days,
+            DEFAULT_EXPIRY_THRESHOLD_DAYS
+        );
+    }
+
+    #[test]
+    fn test_handshake_from_url_invalid() {
+        let url = Url::parse("tls://example.com?expiry_threshold_days=soon").unwrap();
+
+        match Handshake::from_url(&url) {
+            Err(Error::InvalidUrl { reason, .. }) => {
+                assert_eq!(reason, "expiry_threshold_days must be a number")
+            }
+            other => panic!("Expected InvalidUrl, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_matches_host() {
+        assert!(matches_host("example.com", "example.com"));
+        assert!(matches_host("Example.com", "example.COM"));
+        assert!(matches_host("*.example.com", "www.example.com"));
+        assert!(!matches_host("*.example.com", "example.com"));
+        assert!(!matches_host("*.example.com", "a.b.example.com"));
+        assert!(!matches_host("example.com", "example.org"));
+    }
+
+    #[test]
+    fn test_certificate_verify() {
+        let certificate = Certificate {
+            days_until_expiry: 90.0,
+            issuer: "CN=test".to_string(),
+            subject_alternative_names: vec!["example.com".to_string()],
+            san_match: true,
+            chain_valid: true,
+        };
+        assert!(certificate.verify().is_ok());
+        assert!(!certificate.is_expiring(14.0));
+        assert!(certificate.is_expiring(120.0));
+
+        for (certificate, expected) in [
+            (
+                Certificate {
+                    days_until_expiry: -1.0,
+                    ..certificate.clone()
+                },
+                "certificate has expired",
+            ),
+            (
+                Certificate {
+                    chain_valid: false,
+                    ..certificate.clone()
+                },
+                "certificate chain is not trusted",
+            ),
+            (
+                Certificate {
+                    san_match: false,
+                    ..certificate.clone()
+                },
+                "certificate does not match host",
+            ),
+        ] {
+            match certificate.verify() {
+                Err(Error::TlsCertificateInvalid { reason }) => assert_eq!(reason, expected),
+                other => panic!("Expected TlsCertificateInvalid, got {:?}", other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handshake_inspect_self_signed() {
+        let addr = tls_server(params(vec!["localhost"]), None).await;
+        let url = Url::parse(&format!("tls://localhost:{}", addr.port())).unwrap();
+
+        let certificate = Handshake::from_url(&url)
+            .unwrap()
+            .inspect(&TlsOptions::default(), 1000, 1000)
+            .await
+            .unwrap();
+
+        assert_eq!(certificate.issuer, "CN=netcheck test ca");
+        assert_eq!(
+            certificate.subject_alternative_names,
+            vec!["localhost".to_string()]
+        );
+        assert_eq!(certificate.san_match, true);
+        assert_eq!(certificate.chain_valid, false);
+        assert!(certificate.days_until_expiry > 365.0);
+    }
+
+    #[tokio::test]
+    async fn test_handshake_inspect_san_mismatch() {
+        let addr = tls_server(params(vec!["example.com"]), None).await;
+        let url = Url::parse(&format!("tls://localhost:{}", addr.port())).unwrap();
+
+        let certificate = Handshake::from_url(&url)
+            .unwrap()
+            .inspect(&TlsOptions::default(), 1000, 1000)
+            .await
+            .unwrap();
+
+        assert_eq!(certificate.san_match, false);
+    }
+
+    #[tokio::test]
+    async fn test_handshake_inspect_expiring() {
+        let expires = chrono::Utc::now() + chrono::TimeDelta::try_days(3).unwrap();
+        let mut params = params(vec!["localhost"]);
+        params.not_after =
+            rcgen::date_time_ymd(expires.year(), expires.month() as u8, expires.day() as u8);
+        let addr = tls_server(params, None).await;
+        let url = Url::parse(&format!("tls://localhost:{}", addr.port())).unwrap();
+
+        let certificate = Handshake::from_url(&url)
+            .unwrap()
+            .inspect(&TlsOptions::default(), 1000, 1000)
+            .await
+            .unwrap();
+
+        assert!(certificate.days_until_expiry > 1.0);
+        assert!(certificate.days_until_expiry <= 3.0);
+        assert!(certificate.is_expiring(DEFAULT_EXPIRY_THRESHOLD_DAYS));
+    }
+
+    #[tokio::test]
+    async fn test_handshake_inspect_tls_options() {
+        let mut ca = CertificateParams::new(vec![]);
+        ca.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        ca.distinguished_name
+            .push(DnType::CommonName, "netcheck private ca");
+        let ca = rcgen::Certificate::from_params(ca).unwrap();
+        let addr = tls_server(params(vec!["localhost"]), Some(&ca)).await;
+        let url = Url::parse(&format!("tls://127.0.0.1:{}", addr.port())).unwrap();
+        let handshake = Handshake::from_url(&url).unwrap();
+
+        let certificate = handshake
+            .inspect(&TlsOptions::default(), 1000, 1000)
+            .await
+            .unwrap();
+        assert_eq!(certificate.chain_valid, false);
+        assert_eq!(certificate.san_match, false);
+
+        let tls = TlsOptions {
+            root_certificates: certificates_from_pem(ca.serialize_pem().unwrap().as_bytes())
+                .unwrap(),
+            server_name: Some("localhost".to_string()),
+            ..TlsOptions::default()
+        };
+        let certificate = handshake.inspect(&tls, 1000, 1000).await.unwrap();
+        assert_eq!(certificate.chain_valid, true);
+        assert_eq!(certificate.san_match, true);
+        assert!(certificate.verify().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_handshake_inspect_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!(
+            "tls://127.0.0.1:{}",
+            listener.local_addr().unwrap().port()
+        ))
+        .unwrap();
+        drop(listener);
+
+        match Handshake::from_url(&url)
+            .unwrap()
+            .inspect(&TlsOptions::default(), 1000, 1000)
+            .await
+        {
+            Err(Error::IoError { .. }) => {}
+            other => panic!("Expected IoError, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub use self::supervisor::{Health, Reconciliation, Shutdown, Supervisor};
+
+#[allow(clippy::module_inception)]
+mod supervisor;
@@ -0,0 +1,437 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::task::JoinHandle;
+use tokio::time;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info};
+
+use crate::config::{self, TargetConfig};
+use crate::notifier::Notifier;
+use crate::runner::Registry;
+
+/// Supervisor owns the runner tasks of each target and reconciles them with the configured targets.
+#[derive(Debug, Default)]
+pub struct Supervisor {
+    runners: HashMap<String, Supervised>,
+    registry: Registry,
+    notifier: Notifier,
+    shutdown: CancellationToken,
+}
+
+#[derive(Debug)]
+struct Supervised {
+    target: TargetConfig,
+    handle: JoinHandle<()>,
+    stop: CancellationToken,
+}
+
+/// Reconciliation lists the target names affected by a call to `Supervisor::reconcile`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Reconciliation {
+    pub started: Vec<String>,
+    pub restarted: Vec<String>,
+    pub stopped: Vec<String>,
+    pub unchanged: Vec<String>,
+}
+
+/// Health lists the targets by the state of their runner task.
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct Health {
+    /// Targets whose runner is alive and has reported a result.
+    pub running: Vec<String>,
+    /// Targets whose runner is alive but has not reported a result yet.
+    pub pending: Vec<String>,
+    /// Targets whose runner task has exited.
+    pub dead: Vec<String>,
+}
+
+impl Health {
+    /// Whether every runner task is alive.
+    pub fn is_live(&self) -> bool {
+        self.dead.is_empty()
+    }
+
+    /// Whether every runner task is alive and has reported a result.
+    pub fn is_ready(&self) -> bool {
+        self.is_live() && self.pending.is_empty()
+    }
+}
+
+/// Shutdown holds the runner tasks stopping after a call to `Supervisor::shutdown`.
+#[derive(Debug)]
+pub struct Shutdown {
+    handles: Vec<(String, JoinHandle<()>)>,
+}
+
+impl Shutdown {
+    /// Wait for every runner task to stop, aborting the runners still running after the timeout.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout`: How long to wait for the runners in total.
+    ///
+    /// returns: Vec<String>, the names of the targets whose runner was aborted.
+    pub async fn wait(
+        self,
+        timeout: Duration,
+    ) -> Vec<String> {
+        let deadline = time::Instant::now() + timeout;
+        let mut aborted = Vec::new();
+        for (name, mut handle) in self.handles {
+            if time::timeout_at(deadline, &mut handle).await.is_err() {
+                handle.abort();
+                aborted.push(name);
+            }
+        }
+        aborted.sort();
+
+        aborted
+    }
+}
+
+impl Supervised {
+    /// Abort the runner task. The runner is cancelled first so a check still in flight can not
+    /// report after the target is removed from the registry.
+    fn abort(self) {
+        self.stop.cancel();
+        self.handle.abort();
+    }
+}
+
+impl Supervisor {
+    /// Create a new Supervisor with no running targets.
+    ///
+    /// returns: Supervisor
+    pub fn new() -> Supervisor {
+        Supervisor::default()
+    }
+
+    /// Create a new Supervisor whose runners publish their status to the registry.
+    ///
+    /// # Arguments
+    ///
+    /// * `registry`: The registry shared with the status API.
+    ///
+    /// returns: Supervisor
+    pub fn with_registry(registry: Registry) -> Supervisor {
+        Supervisor {
+            registry,
+            ..Default::default()
+        }
+    }
+
+    /// Set the notifier the runners send status changes of their target to.
+    ///
+    /// # Arguments
+    ///
+    /// * `notifier`: The notifier shared by every runner.
+    ///
+    /// returns: Supervisor
+    pub fn with_notifier(
+        mut self,
+        notifier: Notifier,
+    ) -> Supervisor {
+        self.notifier = notifier;
+        self
+    }
+
+    /// Reconcile the running runners with the targets.
+    ///
+    /// New targets are started, targets which are no longer present are stopped, and targets
+    /// whose settings changed are restarted. Unchanged targets keep running so their status is
+    /// kept. Nothing is changed if any of the targets can not be built.
+    ///
+    /// # Arguments
+    ///
+    /// * `targets`: The resolved targets which should be running.
+    ///
+    /// returns: Result<Reconciliation, Error>
+    pub fn reconcile(
+        &mut self,
+        targets: Vec<TargetConfig>,
+    ) -> Result<Reconciliation, config::Error> {
+        let mut reconciliation = Reconciliation::default();
+        let mut pending = Vec::new();
+        for target in targets {
+            match self.runners.get(&target.name) {
+                Some(running) if running.target == target && !running.handle.is_finished() => {
+                    reconciliation.unchanged.push(target.name.clone());
+                }
+                running => {
+                    let runner = target
+                        .runner_builder()?
+                        .registry(self.registry.clone())
+                        .notifier(self.notifier.clone())
+                        .build();
+                    if running.is_some() {
+                        reconciliation.restarted.push(target.name.clone());
+                    } else {
+                        reconciliation.started.push(target.name.clone());
+                    }
+                    pending.push((target, runner));
+                }
+            }
+        }
+
+        let mut stopped: Vec<String> = self
+            .runners
+            .keys()
+            .filter(|name| {
+                !reconciliation.unchanged.contains(name)
+                    && !pending.iter().any(|(target, _)| &target.name == *name)
+            })
+            .cloned()
+            .collect();
+        stopped.sort();
+        for name in &stopped {
+            if let Some(running) = self.runners.remove(name) {
+                running.abort();
+            }
+            self.registry.remove(name);
+        }
+        reconciliation.stopped = stopped;
+
+        for (target, runner) in pending {
+            if let Some(running) = self.runners.remove(&target.name) {
+                running.abort();
+            }
+            // The report of a restarted runner is stale until the new runner reports.
+            self.registry.remove(&target.name);
+
+            let name = target.name.clone();
+            let stop = self.shutdown.child_token();
+            let shutdown = stop.clone();
+            let handle = tokio::spawn(async move {
+                if let Err(e) = runner.run_until(shutdown).await {
+                    error!(runner_target = name, "handler error: {}", e);
+                }
+            });
+            self.runners.insert(
+                target.name.clone(),
+                Supervised {
+                    target,
+                    handle,
+                    stop,
+                },
+            );
+        }
+
+        info!(
+            started = reconciliation.started.join(","),
+            restarted = reconciliation.restarted.join(","),
+            stopped = reconciliation.stopped.join(","),
+            unchanged = reconciliation.unchanged.join(","),
+            "reconciled targets"
+        );
+
+        Ok(reconciliation)
+    }
+
+    /// The names of the targets being run.
+    pub fn targets(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.runners.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// The health of the runner tasks, a runner is pending until it reports its first result.
+    pub fn health(&self) -> Health {
+        let mut health = Health::default();
+        for name in self.targets() {
+            let running = &self.runners[&name];
+            if running.handle.is_finished() {
+                health.dead.push(name);
+            } else if self.registry.target(&name).is_none() {
+                health.pending.push(name);
+            } else {
+                health.running.push(name);
+            }
+        }
+
+        health
+    }
+
+    /// Signal every runner to stop once its in-flight check is finished, returning the runner
+    /// tasks to wait for. The Supervisor can not start runners afterwards.
+    ///
+    /// returns: Shutdown
+    pub fn shutdown(&mut self) -> Shutdown {
+        self.shutdown.cancel();
+
+        Shutdown {
+            handles: self
+                .runners
+                .drain()
+                .map(|(name, running)| (name, running.handle))
+                .collect(),
+        }
+    }
+
+    /// Stop every runner.
+    pub fn abort(&mut self) {
+        for (name, running) in self.runners.drain() {
+            running.abort();
+            self.registry.remove(&name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::config::{ProbeKind, RunnerConfig};
+    use crate::runner::{Quorum, Status, TargetReport, TargetStatus};
+
+    fn target(
+        name: &str,
+        wait_time_seconds: u64,
+    ) -> TargetConfig {
+        TargetConfig {
+            name: name.to_string(),
+            urls: vec!["127.0.0.1:1".to_string()],
+            probe: ProbeKind::Tcp,
+            runner: RunnerConfig {
+                wait_time_seconds: Some(wait_time_seconds),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_reconcile() {
+        let mut supervisor = Supervisor::new();
+
+        let reconciliation = supervisor
+            .reconcile(vec![target("a", 1), target("b", 1), target("c", 1)])
+            .unwrap();
+        assert_eq!(
+            reconciliation,
+            Reconciliation {
+                started: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+                ..Default::default()
+            }
+        );
+        assert_eq!(supervisor.targets(), vec!["a", "b", "c"]);
+
+        let reconciliation = supervisor
+            .reconcile(vec![target("a", 1), target("b", 2), target("d", 1)])
+            .unwrap();
+        assert_eq!(
+            reconciliation,
+            Reconciliation {
+                started: vec!["d".to_string()],
+                restarted: vec!["b".to_string()],
+                stopped: vec!["c".to_string()],
+                unchanged: vec!["a".to_string()],
+            }
+        );
+        assert_eq!(supervisor.targets(), vec!["a", "b", "d"]);
+
+        supervisor.abort();
+        assert_eq!(supervisor.targets(), Vec::<String>::new());
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_reconcile_keeps_running_task() {
+        let mut supervisor = Supervisor::new();
+        supervisor.reconcile(vec![target("a", 1)]).unwrap();
+        let started = supervisor.runners["a"].handle.abort_handle();
+
+        supervisor.reconcile(vec![target("a", 1)]).unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        assert_eq!(supervisor.runners["a"].handle.is_finished(), false);
+        assert_eq!(started.is_finished(), false);
+
+        supervisor.reconcile(vec![]).unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        assert_eq!(started.is_finished(), true);
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_reconcile_invalid_target() {
+        let mut supervisor = Supervisor::new();
+        supervisor.reconcile(vec![target("a", 1)]).unwrap();
+
+        let mut invalid = target("b", 1);
+        invalid.urls = vec!["http://".to_string()];
+
+        match supervisor.reconcile(vec![invalid]) {
+            Err(config::Error::InvalidTarget { target, .. }) => assert_eq!(target, "b"),
+            other => panic!("Expected InvalidTarget, got {:?}", other),
+        }
+        assert_eq!(supervisor.targets(), vec!["a"]);
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_reconcile_removes_stopped_from_registry() {
+        let registry = Registry::new();
+        let mut supervisor = Supervisor::with_registry(registry.clone());
+        supervisor.reconcile(vec![target("a", 1)]).unwrap();
+
+        let runner = target("a", 1).target().unwrap();
+        let status = TargetStatus::new(Quorum::Any, vec![Status::new(1, 1)]);
+        registry.update(TargetReport::new(&runner, &status));
+        assert!(registry.target("a").is_some());
+
+        supervisor.reconcile(vec![]).unwrap();
+        assert_eq!(registry.target("a"), None);
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_health() {
+        let registry = Registry::new();
+        let mut supervisor = Supervisor::with_registry(registry.clone());
+        assert!(supervisor.health().is_ready());
+
+        // The server never answers, so the first check of "a" is still running during the test.
+        let silent = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut slow = target("a", 60);
+        slow.urls = vec![format!("http://{}", silent.local_addr().unwrap())];
+        slow.probe = ProbeKind::Http;
+        slow.runner.timeout_ms = Some(60_000);
+        supervisor.reconcile(vec![slow, target("b", 60)]).unwrap();
+        for _ in 0..100 {
+            if registry.target("b").is_some() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let health = supervisor.health();
+        assert_eq!(
+            health,
+            Health {
+                running: vec!["b".to_string()],
+                pending: vec!["a".to_string()],
+                dead: vec![],
+            }
+        );
+        assert!(health.is_live());
+        assert!(!health.is_ready());
+
+        supervisor.runners["b"].handle.abort();
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        let health = supervisor.health();
+        assert_eq!(health.dead, vec!["b".to_string()]);
+        assert!(!health.is_live());
+        assert!(!health.is_ready());
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_shutdown() {
+        let mut supervisor = Supervisor::new();
+        let mut slow = target("b", 60);
+        slow.runner.start_offset_ms = Some(60_000);
+        supervisor.reconcile(vec![target("a", 60), slow]).unwrap();
+
+        let aborted = supervisor.shutdown().wait(Duration::from_secs(1)).await;
+        assert_eq!(aborted, Vec::<String>::new());
+        assert_eq!(supervisor.targets(), Vec::<String>::new());
+    }
+}